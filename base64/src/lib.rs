@@ -1,134 +1,529 @@
 pub mod base64 {
 
-    use core::panic;
+    use std::fmt;
+    use std::io::{self, Read, Write};
+    use std::str::FromStr;
 
-    // These variables govern the alphabet that is being encoded or decoded.
-    const UPPERCASEOFFSET: i8 = 65;
-    const LOWERCASEOFFSET: i8 = 71;
-    const DIGITOFFSET: i8 = -4;
-    const PADDING: &str = "=";
+    const PADDING: char = '=';
 
-    pub fn encode(input: Vec<u8>) -> String {
-        let mut output: String = String::new();
+    // Marks bytes that aren't part of an alphabet in its decode table.
+    const INVALID: u8 = 0xff;
+
+    // Bytes read from the inner reader at a time when stream decoding.
+    const BUFFER_SIZE: usize = 8 * 1024;
+
+    // The 64 symbols used to encode, along with the reverse lookup used to decode.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct Alphabet {
+        symbols: [u8; 64],
+        decode_table: [u8; 256],
+    }
+
+    impl Alphabet {
+        // Builds an alphabet from symbols known to be valid, used for the
+        // built in alphabets so they can be constants.
+        const fn from_symbols(symbols: &[u8; 64]) -> Alphabet {
+            let mut decode_table = [INVALID; 256];
 
-        let _ = input
-            .chunks(3)
-            .for_each(|x| output.push_str(&String::from_utf8(encode_chunk(x)).unwrap()));
+            let mut i = 0;
+            while i < 64 {
+                decode_table[symbols[i] as usize] = i as u8;
+                i += 1;
+            }
 
-        // add padding
-        let len = output.len() % 4;
-        if len != 0 {
-            for _ in 1..=len {
-                output.push_str(PADDING)
+            Alphabet {
+                symbols: *symbols,
+                decode_table,
             }
         }
 
-        output
+        // User supplied alphabet, must be 64 distinct printable ASCII symbols
+        // and can't contain the padding character.
+        pub fn new(symbols: &str) -> Result<Alphabet, AlphabetError> {
+            let count = symbols.chars().count();
+            if count != 64 {
+                return Err(AlphabetError::InvalidLength(count));
+            }
+
+            for (i, symbol) in symbols.char_indices() {
+                if !symbol.is_ascii_graphic() || symbol == PADDING {
+                    return Err(AlphabetError::InvalidSymbol(symbol));
+                }
+                if symbols[..i].contains(symbol) {
+                    return Err(AlphabetError::DuplicateSymbol(symbol));
+                }
+            }
+
+            let mut table = [0_u8; 64];
+            table.copy_from_slice(symbols.as_bytes());
+
+            Ok(Alphabet::from_symbols(&table))
+        }
+
+        pub fn as_str(&self) -> &str {
+            // only ever built from ASCII symbols
+            std::str::from_utf8(&self.symbols).unwrap()
+        }
+
+        fn encode_index(&self, index: u8) -> char {
+            self.symbols[index as usize] as char
+        }
+
+        // Index of the symbol in the alphabet, None if it isn't part of it.
+        fn decode_index(&self, symbol: char) -> Option<u8> {
+            if !symbol.is_ascii() {
+                return None;
+            }
+
+            match self.decode_table[symbol as usize] {
+                INVALID => None,
+                index => Some(index),
+            }
+        }
+    }
+
+    // RFC 4648 section 4.
+    pub const STANDARD_ALPHABET: Alphabet =
+        Alphabet::from_symbols(b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/");
+
+    // RFC 4648 section 5, safe for urls and filenames. Used by JWTs.
+    pub const URL_SAFE_ALPHABET: Alphabet =
+        Alphabet::from_symbols(b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_");
+
+    // RFC 3501 modified UTF-7 mailbox names.
+    pub const IMAP_ALPHABET: Alphabet =
+        Alphabet::from_symbols(b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+,");
+
+    // Used for salts and hashes in bcrypt.
+    pub const BCRYPT_ALPHABET: Alphabet =
+        Alphabet::from_symbols(b"./ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789");
+
+    // Used by the traditional unix crypt(3) hashes.
+    pub const CRYPT_ALPHABET: Alphabet =
+        Alphabet::from_symbols(b"./0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz");
+
+    // Accepts the name of a built in alphabet or a custom 64 symbol alphabet.
+    impl FromStr for Alphabet {
+        type Err = AlphabetError;
+
+        fn from_str(name: &str) -> Result<Alphabet, AlphabetError> {
+            match name {
+                "standard" => Ok(STANDARD_ALPHABET),
+                "url" => Ok(URL_SAFE_ALPHABET),
+                "imap" => Ok(IMAP_ALPHABET),
+                "bcrypt" => Ok(BCRYPT_ALPHABET),
+                "crypt" => Ok(CRYPT_ALPHABET),
+                custom => Alphabet::new(custom),
+            }
+        }
     }
 
-    fn encode_chunk(input: &[u8]) -> Vec<u8> {
-        build_chunk(input).iter().map(|v| encode_index(v)).collect()
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub enum AlphabetError {
+        // Number of symbols supplied instead of 64.
+        InvalidLength(usize),
+        DuplicateSymbol(char),
+        // Non printable ASCII symbol or the padding character.
+        InvalidSymbol(char),
     }
 
-    fn build_chunk(input: &[u8]) -> Vec<u8> {
-        match input.len() {
-            1 => return vec![&input[0] >> 2, (&input[0] & 0b00000011) << 4],
-            2 => {
-                return vec![
-                    (&input[0] >> 2),
-                    (&input[0] & 0b00000011) << 4 | (&input[1] >> 4),
-                    (&input[1] & 0b00001111) << 2,
-                ]
-            }
-            3 => {
-                return vec![
-                    (&input[0] >> 2),
-                    (&input[0] & 0b00000011) << 4 | (&input[1] >> 4),
-                    (&input[1] & 0b00001111) << 2 | (&input[2] & 0b11000000) >> 6,
-                    (&input[2] & 0b00111111),
-                ]
+    impl fmt::Display for AlphabetError {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            match self {
+                AlphabetError::InvalidLength(count) => write!(
+                    f,
+                    "base64 alphabet must have 64 symbols, found {count} (or use standard, url, imap, bcrypt, crypt)"
+                ),
+                AlphabetError::DuplicateSymbol(symbol) => {
+                    write!(f, "base64 alphabet has duplicate symbol {symbol:?}")
+                }
+                AlphabetError::InvalidSymbol(symbol) => {
+                    write!(f, "base64 alphabet can't contain symbol {symbol:?}")
+                }
             }
-            _ => unreachable!(),
         }
     }
 
-    fn encode_index(input: &u8) -> u8 {
-        // stolen from internet, not sure where. some base64 implementation blog.
-        // We know that 2^6 can't exceed these values so will get a mapping
+    impl std::error::Error for AlphabetError {}
 
-        // This function finds the relative offset into the b64 alphabet and
-        // returns the index.
-        let ascii_index = match input {
-            0..=25 => *input as i8 + UPPERCASEOFFSET,
-            26..=51 => *input as i8 + LOWERCASEOFFSET,
-            52..=61 => *input as i8 + DIGITOFFSET,
-            62 => 43,
-            63 => 47,
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum PaddingMode {
+        // Encoding pads to a whole group, decoding requires it.
+        Required,
+        // Encoding pads to a whole group, decoding accepts it with or without.
+        Optional,
+        // Encoding never pads, decoding rejects any padding.
+        Forbidden,
+    }
 
-            _ => unreachable!(),
-        } as u8;
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum LineEnding {
+        Lf,
+        CrLf,
+    }
+
+    impl LineEnding {
+        pub fn as_str(&self) -> &'static str {
+            match self {
+                LineEnding::Lf => "\n",
+                LineEnding::CrLf => "\r\n",
+            }
+        }
+    }
 
-        ascii_index
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct Config {
+        padding: PaddingMode,
+        strict: bool,
+        lenient: bool,
+        line_wrap: Option<(usize, LineEnding)>,
     }
 
-    pub fn decode(input: &str) -> Vec<u8> {
-        // handle padding string
-        let input = if input.contains('=') {
-            input.split('=').take(1).next().unwrap()
-        } else {
-            input
-        };
+    impl Config {
+        pub const fn new() -> Config {
+            Config {
+                padding: PaddingMode::Required,
+                strict: false,
+                lenient: false,
+                line_wrap: None,
+            }
+        }
+
+        pub const fn with_padding(self, padding: PaddingMode) -> Config {
+            Config { padding, ..self }
+        }
+
+        // Reject encodings whose final symbol has bits set that an encoder would
+        // never produce, so every accepted string is the canonical encoding of
+        // its output.
+        pub const fn with_strict(self, strict: bool) -> Config {
+            Config { strict, ..self }
+        }
+
+        // Skip ASCII whitespace, including CR and LF, when decoding so wrapped
+        // and indented input decodes as if it were one line.
+        pub const fn with_lenient(self, lenient: bool) -> Config {
+            Config { lenient, ..self }
+        }
 
-        if validate_input(input) {
-            Vec::<u8>::from(input)
-                .chunks(4)
-                .map(|x| decode_chunk(x))
-                .flatten()
-                .filter(|x| *x > 0_u8)
-                .collect()
-        } else {
-            panic!("Invalid base64 string to decode.");
+        // Break encoded output into lines of at most width symbols, a width of
+        // zero leaves it on one line.
+        pub const fn with_line_wrap(self, width: usize, ending: LineEnding) -> Config {
+            Config {
+                line_wrap: Some((width, ending)),
+                ..self
+            }
         }
     }
 
-    fn validate_input(input: &str) -> bool {
-        let res = input.len() % 4 == 0;
+    impl Default for Config {
+        fn default() -> Config {
+            Config::new()
+        }
+    }
 
-        res && input
-            .chars()
-            .map(|x| in_alphabet_domain(x))
-            .fold(res, |f, x| f && x)
+    // Encodes and decodes with one alphabet and configuration.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct Engine {
+        alphabet: Alphabet,
+        config: Config,
     }
 
-    fn in_alphabet_domain(candidate: char) -> bool {
-        match candidate as u8 {
-            // base64 characters
-            65..=90 => true,
-            97..=122 => true,
-            48..=57 => true,
-            43 => true,
-            47 => true,
+    pub const STANDARD: Engine = Engine::new(STANDARD_ALPHABET, Config::new());
+
+    pub const STANDARD_NO_PAD: Engine = Engine::new(
+        STANDARD_ALPHABET,
+        Config::new().with_padding(PaddingMode::Forbidden),
+    );
+
+    // RFC 2045, 76 symbol CRLF terminated lines.
+    pub const MIME: Engine = Engine::new(
+        STANDARD_ALPHABET,
+        Config::new()
+            .with_lenient(true)
+            .with_line_wrap(76, LineEnding::CrLf),
+    );
+
+    // RFC 7468, 64 symbol lines.
+    pub const PEM: Engine = Engine::new(
+        STANDARD_ALPHABET,
+        Config::new()
+            .with_lenient(true)
+            .with_line_wrap(64, LineEnding::Lf),
+    );
+
+    pub const URL_SAFE: Engine = Engine::new(URL_SAFE_ALPHABET, Config::new());
+
+    pub const URL_SAFE_NO_PAD: Engine = Engine::new(
+        URL_SAFE_ALPHABET,
+        Config::new().with_padding(PaddingMode::Forbidden),
+    );
+
+    impl Engine {
+        pub const fn new(alphabet: Alphabet, config: Config) -> Engine {
+            Engine { alphabet, config }
+        }
+
+        pub fn alphabet(&self) -> &Alphabet {
+            &self.alphabet
+        }
+
+        pub fn config(&self) -> &Config {
+            &self.config
+        }
+
+        pub fn encode(&self, input: &[u8]) -> String {
+            let mut writer = EncoderWriter::new(Vec::with_capacity(input.len() / 3 * 4 + 4), *self);
+
+            // writing into a Vec can't fail
+            writer.write_all(input).unwrap();
+            let output = writer.finish().unwrap();
+
+            // encoded output is only ever ASCII
+            String::from_utf8(output).unwrap()
+        }
+
+        pub fn decode(&self, input: &str) -> Result<Vec<u8>, DecodeError> {
+            let mut decoder = SymbolDecoder::new(*self);
+            let mut output = Vec::with_capacity(input.len() / 4 * 3 + 3);
+
+            for (offset, symbol) in input.char_indices() {
+                decoder.push(offset, symbol, &mut output)?;
+            }
+            decoder.finish(&mut output)?;
+
+            Ok(output)
+        }
+
+        // Appends the symbols for up to 3 bytes, plus any padding they need.
+        fn encode_group(&self, chunk: &[u8], output: &mut String) {
+            // pack the chunk into the top of a 24 bit group, missing bytes are zero
+            let group = chunk
+                .iter()
+                .enumerate()
+                .fold(0_u32, |acc, (i, x)| acc | (*x as u32) << (16 - 8 * i));
 
-            // non-base64 characters
-            _ => false,
+            // n bytes carry enough bits for n + 1 symbols, the rest is padding
+            for i in 0..=chunk.len() {
+                let index = (group >> (18 - 6 * i)) & 0b00111111;
+                output.push(self.alphabet.encode_index(index as u8));
+            }
+
+            if self.config.padding != PaddingMode::Forbidden {
+                for _ in chunk.len()..3 {
+                    output.push(PADDING);
+                }
+            }
         }
     }
 
-    fn decode_chunk(input_pre: &[u8]) -> Vec<u8> {
-        let input = input_pre
-            .iter()
-            .map(|x| decode_index(*x))
-            .collect::<Vec<u8>>();
+    // Decoding state fed one symbol at a time, so whole strings and streams go
+    // through the same validation and report the same offsets.
+    struct SymbolDecoder {
+        engine: Engine,
+        group: [u8; 4],
+        group_len: usize,
+        // offset of the first symbol, data or padding, in the current group
+        group_start: usize,
+        symbol_count: usize,
+        last_symbol: (usize, char),
+        // offset of the first padding symbol once padding has started
+        padding_start: Option<usize>,
+        padding_len: usize,
+    }
 
-        match input_pre.len() {
-            2 => vec![
-                (input[0] & 0b00111111) << 2 | input[1] >> 4,
-                (input[1] & 0b00001111) << 4,
-            ],
+    impl SymbolDecoder {
+        fn new(engine: Engine) -> SymbolDecoder {
+            SymbolDecoder {
+                engine,
+                group: [0; 4],
+                group_len: 0,
+                group_start: 0,
+                symbol_count: 0,
+                last_symbol: (0, PADDING),
+                padding_start: None,
+                padding_len: 0,
+            }
+        }
+
+        fn push(
+            &mut self,
+            offset: usize,
+            symbol: char,
+            output: &mut Vec<u8>,
+        ) -> Result<(), DecodeError> {
+            // whitespace, even a trailing newline, has to be asked for
+            if symbol.is_ascii_whitespace() {
+                if self.engine.config.lenient {
+                    return Ok(());
+                }
+                return Err(DecodeError::InvalidSymbol { offset, symbol });
+            }
+
+            if symbol == PADDING {
+                if self.engine.config.padding == PaddingMode::Forbidden {
+                    return Err(DecodeError::InvalidPadding { offset });
+                }
+
+                let padding_start = *self.padding_start.get_or_insert(offset);
+                self.padding_len += 1;
+
+                // a group holds at least 2 symbols, so at most 2 of its 4 can be padding
+                if self.padding_len > 2 {
+                    return Err(DecodeError::InvalidPadding {
+                        offset: padding_start,
+                    });
+                }
+
+                self.count_symbol(offset);
+                return Ok(());
+            }
+
+            // padding is only valid as a trailing run
+            if let Some(padding_start) = self.padding_start {
+                return Err(DecodeError::InvalidPadding {
+                    offset: padding_start,
+                });
+            }
+
+            let index = self
+                .engine
+                .alphabet
+                .decode_index(symbol)
+                .ok_or(DecodeError::InvalidSymbol { offset, symbol })?;
+
+            self.count_symbol(offset);
+            self.last_symbol = (offset, symbol);
+            self.group[self.group_len] = index;
+            self.group_len += 1;
+
+            if self.group_len == 4 {
+                output.extend(decode_chunk(&self.group));
+                self.group_len = 0;
+            }
+
+            Ok(())
+        }
+
+        fn count_symbol(&mut self, offset: usize) {
+            if self.symbol_count.is_multiple_of(4) {
+                self.group_start = offset;
+            }
+            self.symbol_count += 1;
+        }
+
+        // Checks the end of the input and decodes any partial final group.
+        fn finish(&mut self, output: &mut Vec<u8>) -> Result<(), DecodeError> {
+            // padded input has to be whole groups, unpadded input can stop short
+            // of one as long as the last group can hold a byte
+            let padded =
+                self.padding_start.is_some() || self.engine.config.padding == PaddingMode::Required;
+            if (padded && !self.symbol_count.is_multiple_of(4)) || self.group_len == 1 {
+                return Err(DecodeError::InvalidLength {
+                    offset: self.group_start,
+                });
+            }
+
+            if self.engine.config.strict {
+                self.validate_trailing_bits()?;
+            }
+
+            if self.group_len > 0 {
+                output.extend(decode_chunk(&self.group[..self.group_len]));
+                self.group_len = 0;
+            }
+
+            Ok(())
+        }
+
+        fn validate_trailing_bits(&self) -> Result<(), DecodeError> {
+            // bits of the final symbol that fall past the last whole output byte
+            let unused_mask = match self.group_len {
+                2 => 0b00001111,
+                3 => 0b00000011,
+                _ => return Ok(()),
+            };
+
+            if self.group[self.group_len - 1] & unused_mask != 0 {
+                let (offset, symbol) = self.last_symbol;
+                return Err(DecodeError::InvalidTrailingBits { offset, symbol });
+            }
+
+            Ok(())
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub enum DecodeError {
+        // Input isn't a whole number of 4 character groups, offset is the start
+        // of the incomplete trailing group.
+        InvalidLength { offset: usize },
+        // Character outside of the base64 alphabet.
+        InvalidSymbol { offset: usize, symbol: char },
+        // Padding character anywhere other than the end of the input, too many
+        // of them, or any at all when padding is forbidden.
+        InvalidPadding { offset: usize },
+        // Final symbol carries set bits that don't belong to any output byte,
+        // only rejected by strict decoding.
+        InvalidTrailingBits { offset: usize, symbol: char },
+    }
+
+    impl DecodeError {
+        // Byte offset into the input where decoding failed.
+        pub fn offset(&self) -> usize {
+            match self {
+                DecodeError::InvalidLength { offset }
+                | DecodeError::InvalidSymbol { offset, .. }
+                | DecodeError::InvalidPadding { offset }
+                | DecodeError::InvalidTrailingBits { offset, .. } => *offset,
+            }
+        }
+    }
+
+    impl fmt::Display for DecodeError {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            match self {
+                DecodeError::InvalidLength { offset } => write!(
+                    f,
+                    "invalid base64 length, incomplete group starting at offset {offset}"
+                ),
+                DecodeError::InvalidSymbol { offset, symbol } => {
+                    write!(f, "invalid base64 symbol {symbol:?} at offset {offset}")
+                }
+                DecodeError::InvalidPadding { offset } => {
+                    write!(f, "invalid base64 padding at offset {offset}")
+                }
+                DecodeError::InvalidTrailingBits { offset, symbol } => write!(
+                    f,
+                    "non-canonical base64 symbol {symbol:?} at offset {offset}, trailing bits are set"
+                ),
+            }
+        }
+    }
+
+    impl std::error::Error for DecodeError {}
+
+    pub fn encode(input: Vec<u8>) -> String {
+        STANDARD.encode(&input)
+    }
+
+    pub fn decode(input: &str) -> Result<Vec<u8>, DecodeError> {
+        STANDARD.decode(input)
+    }
+
+    pub fn decode_strict(input: &str) -> Result<Vec<u8>, DecodeError> {
+        Engine::new(STANDARD_ALPHABET, Config::new().with_strict(true)).decode(input)
+    }
+
+    // Takes up to 4 alphabet indices and unpacks the bytes they hold.
+    fn decode_chunk(input: &[u8]) -> Vec<u8> {
+        match input.len() {
+            // partial groups only carry whole bytes, the leftover low bits of
+            // the final symbol are dropped rather than emitted as a zero byte
+            2 => vec![(input[0] & 0b00111111) << 2 | input[1] >> 4],
             3 => vec![
                 (input[0] & 0b00111111) << 2 | input[1] >> 4,
                 (input[1] & 0b00001111) << 4 | input[2] >> 2,
-                (input[2] & 0b00000011) << 6,
             ],
             4 => vec![
                 (input[0] & 0b00111111) << 2 | (input[1] >> 4),
@@ -141,26 +536,232 @@ pub mod base64 {
         }
     }
 
-    fn decode_index(input: u8) -> u8 {
-        // Finds the relative offset into the b64 alphabet and corrects for offset.
-        match input {
-            65..=90 => (input as i8 - UPPERCASEOFFSET) as u8,
-            97..=122 => (input as i8 - LOWERCASEOFFSET) as u8,
-            48..=57 => (input as i8 - DIGITOFFSET) as u8,
-            43 => 62,
-            47 => 63,
+    // Base64 encodes everything written to it into the inner writer. Partial
+    // groups are carried between writes, call finish to write out the final
+    // group and padding.
+    pub struct EncoderWriter<W: Write> {
+        inner: Option<W>,
+        engine: Engine,
+        pending: [u8; 3],
+        pending_len: usize,
+        // symbols written on the current line when wrapping
+        column: usize,
+    }
+
+    impl<W: Write> EncoderWriter<W> {
+        pub fn new(inner: W, engine: Engine) -> EncoderWriter<W> {
+            EncoderWriter {
+                inner: Some(inner),
+                engine,
+                pending: [0; 3],
+                pending_len: 0,
+                column: 0,
+            }
+        }
+
+        // Writes the final partial group and hands back the inner writer.
+        pub fn finish(mut self) -> io::Result<W> {
+            self.write_final()?;
+            Ok(self.inner.take().unwrap())
+        }
+
+        fn write_final(&mut self) -> io::Result<()> {
+            if self.pending_len > 0 {
+                let mut output = String::with_capacity(4);
+                self.engine
+                    .encode_group(&self.pending[..self.pending_len], &mut output);
+                self.pending_len = 0;
+                self.write_symbols(&output)?;
+            }
+
+            match self.inner.as_mut() {
+                Some(inner) => inner.flush(),
+                None => Ok(()),
+            }
+        }
+
+        // Writes encoded symbols to the inner writer, breaking lines as needed.
+        // Line endings go before a symbol rather than after so the output
+        // never ends on one.
+        fn write_symbols(&mut self, symbols: &str) -> io::Result<()> {
+            let output = match self.engine.config.line_wrap {
+                Some((width, ending)) if width > 0 => {
+                    let mut output = String::with_capacity(symbols.len() * 2);
+                    for symbol in symbols.chars() {
+                        if self.column == width {
+                            output.push_str(ending.as_str());
+                            self.column = 0;
+                        }
+                        output.push(symbol);
+                        self.column += 1;
+                    }
+                    output
+                }
+                _ => symbols.to_string(),
+            };
+
+            match self.inner.as_mut() {
+                Some(inner) => inner.write_all(output.as_bytes()),
+                None => Err(io::Error::other("encoder already finished")),
+            }
+        }
+    }
+
+    impl<W: Write> Write for EncoderWriter<W> {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            let mut input = buf;
+            let mut output = String::with_capacity((buf.len() / 3 + 1) * 4);
+
+            // top up a group left over from the last write first
+            if self.pending_len > 0 {
+                let take = (3 - self.pending_len).min(input.len());
+                self.pending[self.pending_len..self.pending_len + take]
+                    .copy_from_slice(&input[..take]);
+                self.pending_len += take;
+                input = &input[take..];
+
+                if self.pending_len < 3 {
+                    return Ok(buf.len());
+                }
+
+                self.engine.encode_group(&self.pending, &mut output);
+                self.pending_len = 0;
+            }
+
+            let whole = input.len() - input.len() % 3;
+            for chunk in input[..whole].chunks(3) {
+                self.engine.encode_group(chunk, &mut output);
+            }
+
+            let rest = &input[whole..];
+            self.pending[..rest.len()].copy_from_slice(rest);
+            self.pending_len = rest.len();
+
+            self.write_symbols(&output)?;
+            Ok(buf.len())
+        }
+
+        // Only flushes whole groups, the final partial group needs finish.
+        fn flush(&mut self) -> io::Result<()> {
+            match self.inner.as_mut() {
+                Some(inner) => inner.flush(),
+                None => Ok(()),
+            }
+        }
+    }
+
+    impl<W: Write> Drop for EncoderWriter<W> {
+        fn drop(&mut self) {
+            // errors can't be reported from drop, call finish to see them
+            if self.inner.is_some() {
+                let _ = self.write_final();
+            }
+        }
+    }
+
+    // Reads base64 from the inner reader and yields the decoded bytes. Decode
+    // errors come back as io::ErrorKind::InvalidData wrapping a DecodeError
+    // whose offset counts bytes from the start of the stream.
+    pub struct DecoderReader<R: Read> {
+        inner: R,
+        decoder: SymbolDecoder,
+        input: Vec<u8>,
+        output: Vec<u8>,
+        output_pos: usize,
+        offset: usize,
+        finished: bool,
+    }
 
-            _ => {
-                unreachable!()
+    impl<R: Read> DecoderReader<R> {
+        pub fn new(inner: R, engine: Engine) -> DecoderReader<R> {
+            DecoderReader {
+                inner,
+                decoder: SymbolDecoder::new(engine),
+                input: vec![0; BUFFER_SIZE],
+                output: Vec::with_capacity(BUFFER_SIZE),
+                output_pos: 0,
+                offset: 0,
+                finished: false,
             }
         }
+
+        // Reads the next block from the inner reader and decodes it.
+        fn fill_output(&mut self) -> io::Result<()> {
+            self.output.clear();
+            self.output_pos = 0;
+
+            let read = self.inner.read(&mut self.input)?;
+
+            if read == 0 {
+                self.finished = true;
+                return self.decoder.finish(&mut self.output).map_err(invalid_data);
+            }
+
+            for &byte in &self.input[..read] {
+                // the stream isn't necessarily UTF-8, anything outside ASCII is
+                // invalid anyway so report it as the replacement character
+                let symbol = if byte.is_ascii() {
+                    byte as char
+                } else {
+                    char::REPLACEMENT_CHARACTER
+                };
+
+                if let Err(e) = self.decoder.push(self.offset, symbol, &mut self.output) {
+                    self.finished = true;
+                    return Err(invalid_data(e));
+                }
+                self.offset += 1;
+            }
+
+            Ok(())
+        }
+    }
+
+    impl<R: Read> Read for DecoderReader<R> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            // a block of whitespace or a partial group decodes to nothing, so
+            // keep reading until there is output or the stream is done
+            while self.output_pos == self.output.len() {
+                if self.finished {
+                    return Ok(0);
+                }
+                self.fill_output()?;
+            }
+
+            let count = buf.len().min(self.output.len() - self.output_pos);
+            buf[..count].copy_from_slice(&self.output[self.output_pos..self.output_pos + count]);
+            self.output_pos += count;
+
+            Ok(count)
+        }
+    }
+
+    fn invalid_data(error: DecodeError) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, error)
     }
 }
 
 #[cfg(test)]
 mod base64_tests {
-    use crate::base64::{decode, encode};
-    //assert_eq!("", encode(Vec::<u8>::from()));
+    use crate::base64::{
+        decode, decode_strict, encode, Alphabet, AlphabetError, Config, DecodeError, DecoderReader,
+        EncoderWriter, Engine, LineEnding, PaddingMode, BCRYPT_ALPHABET, CRYPT_ALPHABET,
+        IMAP_ALPHABET, MIME, PEM, STANDARD, STANDARD_ALPHABET, STANDARD_NO_PAD, URL_SAFE,
+        URL_SAFE_NO_PAD,
+    };
+
+    use std::io::{self, Read, Write};
+
+    // Test vectors from RFC 4648 section 10, indexed by input length.
+    const RFC_VECTORS: [(&str, &str); 7] = [
+        ("", ""),
+        ("f", "Zg=="),
+        ("fo", "Zm8="),
+        ("foo", "Zm9v"),
+        ("foob", "Zm9vYg=="),
+        ("fooba", "Zm9vYmE="),
+        ("foobar", "Zm9vYmFy"),
+    ];
 
     #[test]
     fn test_simple_encode() {
@@ -197,30 +798,31 @@ mod base64_tests {
     fn test_hello_decode() {
         assert_eq!(
             "Hello, World!".to_string().into_bytes(),
-            decode("SGVsbG8sIFdvcmxkIQ==")
+            decode("SGVsbG8sIFdvcmxkIQ==").unwrap()
         );
     }
 
     #[test]
     fn test_character_decode() {
-        assert_eq!("a".to_string().into_bytes(), decode("YQ=="));
+        assert_eq!("a".to_string().into_bytes(), decode("YQ==").unwrap());
     }
 
     #[test]
     fn test_nonalpha_character_decode() {
-        assert_eq!("abcdefghijklmnopqrstuvwxyz123456789!#@$%^*&() 'ABCDEFGHIJKLMNOPQRSTUVYXYZ".to_string().into_bytes(), decode("YWJjZGVmZ2hpamtsbW5vcHFyc3R1dnd4eXoxMjM0NTY3ODkhI0AkJV4qJigpICdBQkNERUZHSElKS0xNTk9QUVJTVFVWWVhZWg=="));
+        assert_eq!("abcdefghijklmnopqrstuvwxyz123456789!#@$%^*&() 'ABCDEFGHIJKLMNOPQRSTUVYXYZ".to_string().into_bytes(), decode("YWJjZGVmZ2hpamtsbW5vcHFyc3R1dnd4eXoxMjM0NTY3ODkhI0AkJV4qJigpICdBQkNERUZHSElKS0xNTk9QUVJTVFVWWVhZWg==").unwrap());
     }
 
     #[test]
     fn test_passage_decode() {
-        assert_eq!("In our previous post, we announced that Android now supports the Rust programming language for developing the OS itself. Related to this, we are also participating in the effort to evaluate the use of Rust as a supported language for developing the Linux kernel. In this post, we discuss some technical aspects of this work using a few simple examples.".to_string().into_bytes(), decode("SW4gb3VyIHByZXZpb3VzIHBvc3QsIHdlIGFubm91bmNlZCB0aGF0IEFuZHJvaWQgbm93IHN1cHBvcnRzIHRoZSBSdXN0IHByb2dyYW1taW5nIGxhbmd1YWdlIGZvciBkZXZlbG9waW5nIHRoZSBPUyBpdHNlbGYuIFJlbGF0ZWQgdG8gdGhpcywgd2UgYXJlIGFsc28gcGFydGljaXBhdGluZyBpbiB0aGUgZWZmb3J0IHRvIGV2YWx1YXRlIHRoZSB1c2Ugb2YgUnVzdCBhcyBhIHN1cHBvcnRlZCBsYW5ndWFnZSBmb3IgZGV2ZWxvcGluZyB0aGUgTGludXgga2VybmVsLiBJbiB0aGlzIHBvc3QsIHdlIGRpc2N1c3Mgc29tZSB0ZWNobmljYWwgYXNwZWN0cyBvZiB0aGlzIHdvcmsgdXNpbmcgYSBmZXcgc2ltcGxlIGV4YW1wbGVzLg=="))
+        assert_eq!("In our previous post, we announced that Android now supports the Rust programming language for developing the OS itself. Related to this, we are also participating in the effort to evaluate the use of Rust as a supported language for developing the Linux kernel. In this post, we discuss some technical aspects of this work using a few simple examples.".to_string().into_bytes(), decode("SW4gb3VyIHByZXZpb3VzIHBvc3QsIHdlIGFubm91bmNlZCB0aGF0IEFuZHJvaWQgbm93IHN1cHBvcnRzIHRoZSBSdXN0IHByb2dyYW1taW5nIGxhbmd1YWdlIGZvciBkZXZlbG9waW5nIHRoZSBPUyBpdHNlbGYuIFJlbGF0ZWQgdG8gdGhpcywgd2UgYXJlIGFsc28gcGFydGljaXBhdGluZyBpbiB0aGUgZWZmb3J0IHRvIGV2YWx1YXRlIHRoZSB1c2Ugb2YgUnVzdCBhcyBhIHN1cHBvcnRlZCBsYW5ndWFnZSBmb3IgZGV2ZWxvcGluZyB0aGUgTGludXgga2VybmVsLiBJbiB0aGlzIHBvc3QsIHdlIGRpc2N1c3Mgc29tZSB0ZWNobmljYWwgYXNwZWN0cyBvZiB0aGlzIHdvcmsgdXNpbmcgYSBmZXcgc2ltcGxlIGV4YW1wbGVzLg==").unwrap())
     }
 
     #[test]
     fn test_encode_decode_symmetry() {
         assert_eq!(
             decode(
-                &encode(Vec::<u8>::from("In our previous post, we announced that Android now supports the Rust programming language for developing the OS itself. Related to this, we are also participating in the effort to evaluate the use of Rust as a supported language for developing the Linux kernel. In this post, we discuss some technical aspects of this work using a few simple examples."))),
+                &encode(Vec::<u8>::from("In our previous post, we announced that Android now supports the Rust programming language for developing the OS itself. Related to this, we are also participating in the effort to evaluate the use of Rust as a supported language for developing the Linux kernel. In this post, we discuss some technical aspects of this work using a few simple examples.")))
+            .unwrap(),
             "In our previous post, we announced that Android now supports the Rust programming language for developing the OS itself. Related to this, we are also participating in the effort to evaluate the use of Rust as a supported language for developing the Linux kernel. In this post, we discuss some technical aspects of this work using a few simple examples.".to_string().into_bytes()
         );
     }
@@ -229,8 +831,458 @@ mod base64_tests {
     fn test_decode_encode_symmetry() {
         assert_eq!(
             encode(
-                decode("SW4gb3VyIHByZXZpb3VzIHBvc3QsIHdlIGFubm91bmNlZCB0aGF0IEFuZHJvaWQgbm93IHN1cHBvcnRzIHRoZSBSdXN0IHByb2dyYW1taW5nIGxhbmd1YWdlIGZvciBkZXZlbG9waW5nIHRoZSBPUyBpdHNlbGYuIFJlbGF0ZWQgdG8gdGhpcywgd2UgYXJlIGFsc28gcGFydGljaXBhdGluZyBpbiB0aGUgZWZmb3J0IHRvIGV2YWx1YXRlIHRoZSB1c2Ugb2YgUnVzdCBhcyBhIHN1cHBvcnRlZCBsYW5ndWFnZSBmb3IgZGV2ZWxvcGluZyB0aGUgTGludXgga2VybmVsLiBJbiB0aGlzIHBvc3QsIHdlIGRpc2N1c3Mgc29tZSB0ZWNobmljYWwgYXNwZWN0cyBvZiB0aGlzIHdvcmsgdXNpbmcgYSBmZXcgc2ltcGxlIGV4YW1wbGVzLg==")),
+                decode("SW4gb3VyIHByZXZpb3VzIHBvc3QsIHdlIGFubm91bmNlZCB0aGF0IEFuZHJvaWQgbm93IHN1cHBvcnRzIHRoZSBSdXN0IHByb2dyYW1taW5nIGxhbmd1YWdlIGZvciBkZXZlbG9waW5nIHRoZSBPUyBpdHNlbGYuIFJlbGF0ZWQgdG8gdGhpcywgd2UgYXJlIGFsc28gcGFydGljaXBhdGluZyBpbiB0aGUgZWZmb3J0IHRvIGV2YWx1YXRlIHRoZSB1c2Ugb2YgUnVzdCBhcyBhIHN1cHBvcnRlZCBsYW5ndWFnZSBmb3IgZGV2ZWxvcGluZyB0aGUgTGludXgga2VybmVsLiBJbiB0aGlzIHBvc3QsIHdlIGRpc2N1c3Mgc29tZSB0ZWNobmljYWwgYXNwZWN0cyBvZiB0aGlzIHdvcmsgdXNpbmcgYSBmZXcgc2ltcGxlIGV4YW1wbGVzLg==").unwrap()),
                 "SW4gb3VyIHByZXZpb3VzIHBvc3QsIHdlIGFubm91bmNlZCB0aGF0IEFuZHJvaWQgbm93IHN1cHBvcnRzIHRoZSBSdXN0IHByb2dyYW1taW5nIGxhbmd1YWdlIGZvciBkZXZlbG9waW5nIHRoZSBPUyBpdHNlbGYuIFJlbGF0ZWQgdG8gdGhpcywgd2UgYXJlIGFsc28gcGFydGljaXBhdGluZyBpbiB0aGUgZWZmb3J0IHRvIGV2YWx1YXRlIHRoZSB1c2Ugb2YgUnVzdCBhcyBhIHN1cHBvcnRlZCBsYW5ndWFnZSBmb3IgZGV2ZWxvcGluZyB0aGUgTGludXgga2VybmVsLiBJbiB0aGlzIHBvc3QsIHdlIGRpc2N1c3Mgc29tZSB0ZWNobmljYWwgYXNwZWN0cyBvZiB0aGlzIHdvcmsgdXNpbmcgYSBmZXcgc2ltcGxlIGV4YW1wbGVzLg=="
         );
     }
+
+    #[test]
+    fn test_invalid_symbol_decode() {
+        assert_eq!(
+            decode("SGVs!G8="),
+            Err(DecodeError::InvalidSymbol {
+                offset: 4,
+                symbol: '!'
+            })
+        );
+        assert_eq!(
+            decode("YWÉh"),
+            Err(DecodeError::InvalidSymbol {
+                offset: 2,
+                symbol: 'É'
+            })
+        );
+    }
+
+    #[test]
+    fn test_invalid_length_decode() {
+        assert_eq!(
+            decode("YWFhYWF"),
+            Err(DecodeError::InvalidLength { offset: 4 })
+        );
+    }
+
+    #[test]
+    fn test_invalid_padding_decode() {
+        assert_eq!(
+            decode("YQ==YWFh"),
+            Err(DecodeError::InvalidPadding { offset: 2 })
+        );
+    }
+
+    #[test]
+    fn test_invalid_padding_count_decode() {
+        assert_eq!(
+            decode("Y==="),
+            Err(DecodeError::InvalidPadding { offset: 1 })
+        );
+        assert_eq!(
+            decode("===="),
+            Err(DecodeError::InvalidPadding { offset: 0 })
+        );
+    }
+
+    #[test]
+    fn test_zero_bytes_decode() {
+        assert_eq!(vec![0_u8], decode("AA==").unwrap());
+        assert_eq!(vec![0_u8, 0], decode("AAA=").unwrap());
+        assert_eq!(vec![0_u8, 0, 0], decode("AAAA").unwrap());
+        assert_eq!(vec![0x61_u8, 0, 0x62], decode("YQBi").unwrap());
+        assert_eq!(Vec::<u8>::new(), decode("").unwrap());
+    }
+
+    #[test]
+    fn test_high_bit_decode() {
+        assert_eq!(vec![0xff_u8, 0xfe, 0x80], decode("//6A").unwrap());
+        assert_eq!(vec![0xfb_u8, 0xff], decode("+/8=").unwrap());
+    }
+
+    #[test]
+    fn test_strict_trailing_bits_decode() {
+        // "YR==" and "YWI=" decode to the same bytes as the canonical forms but
+        // carry set bits in their final symbol
+        assert_eq!(decode("YR==").unwrap(), decode("YQ==").unwrap());
+        assert_eq!(decode("YWJ=").unwrap(), decode("YWI=").unwrap());
+
+        assert_eq!(
+            decode_strict("YR=="),
+            Err(DecodeError::InvalidTrailingBits {
+                offset: 1,
+                symbol: 'R'
+            })
+        );
+        assert_eq!(
+            decode_strict("YWJ="),
+            Err(DecodeError::InvalidTrailingBits {
+                offset: 2,
+                symbol: 'J'
+            })
+        );
+        assert_eq!(vec![0x61_u8], decode_strict("YQ==").unwrap());
+    }
+
+    // Deterministic xorshift so the round trip covers arbitrary bytes without
+    // pulling in a random number crate.
+    fn pseudo_random_bytes(seed: u64, len: usize) -> Vec<u8> {
+        let mut state = seed | 1;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                (state >> 24) as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_round_trip_arbitrary_bytes() {
+        for len in 0..=256 {
+            let zeros = vec![0_u8; len];
+            let high = vec![0xff_u8; len];
+            let random = pseudo_random_bytes(len as u64, len);
+
+            for data in [zeros, high, random] {
+                assert_eq!(data, decode(&encode(data.clone())).unwrap());
+                assert_eq!(data, decode_strict(&encode(data.clone())).unwrap());
+            }
+        }
+    }
+
+    #[test]
+    fn test_rfc_vectors_encode() {
+        for (plain, encoded) in RFC_VECTORS {
+            assert_eq!(encoded, encode(Vec::<u8>::from(plain)));
+            assert_eq!(Vec::<u8>::from(plain), decode_strict(encoded).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_length_matrix_encode() {
+        // "foobar" is two whole groups, so any prefix of it repeated encodes to
+        // the repeated "foobar" encoding followed by the RFC vector for the tail.
+        let data = "foobar".repeat(11).into_bytes();
+
+        for len in 0..=64 {
+            let (_, full) = RFC_VECTORS[6];
+            let (_, tail) = RFC_VECTORS[len % 6];
+            let expected = full.repeat(len / 6) + tail;
+
+            let encoded = encode(data[..len].to_vec());
+            assert_eq!(expected, encoded, "length {len}");
+            assert_eq!(len.div_ceil(3) * 4, encoded.len(), "length {len}");
+            assert_eq!(
+                (3 - len % 3) % 3,
+                encoded.matches('=').count(),
+                "length {len}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_full_alphabet_encode() {
+        let input = vec![
+            0x00, 0x10, 0x83, 0x10, 0x51, 0x87, 0x20, 0x92, 0x8b, 0x30, 0xd3, 0x8f, 0x41, 0x14,
+            0x93, 0x51, 0x55, 0x97, 0x61, 0x96, 0x9b, 0x71, 0xd7, 0x9f, 0x82, 0x18, 0xa3, 0x92,
+            0x59, 0xa7, 0xa2, 0x9a, 0xab, 0xb2, 0xdb, 0xaf, 0xc3, 0x1c, 0xb3, 0xd3, 0x5d, 0xb7,
+            0xe3, 0x9e, 0xbb, 0xf3, 0xdf, 0xbf,
+        ];
+
+        assert_eq!(
+            "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/",
+            encode(input)
+        );
+    }
+
+    #[test]
+    fn test_url_safe_engine() {
+        let data = vec![0xfb_u8, 0xff, 0xbf, 0x3e];
+
+        assert_eq!("+/+/Pg==", STANDARD.encode(&data));
+        assert_eq!("-_-_Pg==", URL_SAFE.encode(&data));
+        assert_eq!("-_-_Pg", URL_SAFE_NO_PAD.encode(&data));
+        assert_eq!(data, URL_SAFE.decode("-_-_Pg==").unwrap());
+        assert_eq!(data, URL_SAFE_NO_PAD.decode("-_-_Pg").unwrap());
+
+        assert_eq!(
+            URL_SAFE.decode("+/+/Pg=="),
+            Err(DecodeError::InvalidSymbol {
+                offset: 0,
+                symbol: '+'
+            })
+        );
+    }
+
+    #[test]
+    fn test_named_alphabets() {
+        let data = Vec::<u8>::from("Hello, World!");
+        let bcrypt = Engine::new(BCRYPT_ALPHABET, Config::new());
+        let crypt = Engine::new(CRYPT_ALPHABET, Config::new());
+        let imap = Engine::new(IMAP_ALPHABET, Config::new());
+
+        assert_eq!("QETqZE6qGDbtakviGO==", bcrypt.encode(&data));
+        assert_eq!("G4JgP4wg63RjQalY6E==", crypt.encode(&data));
+        assert_eq!("+,+,Pg==", imap.encode(&[0xfb, 0xff, 0xbf, 0x3e]));
+
+        for engine in [bcrypt, crypt, imap] {
+            assert_eq!(data, engine.decode(&engine.encode(&data)).unwrap());
+        }
+
+        assert_eq!(URL_SAFE_NO_PAD, {
+            let alphabet = "url".parse::<Alphabet>().unwrap();
+            Engine::new(alphabet, Config::new().with_padding(PaddingMode::Forbidden))
+        });
+        assert_eq!(BCRYPT_ALPHABET, "bcrypt".parse::<Alphabet>().unwrap());
+    }
+
+    #[test]
+    fn test_custom_alphabet() {
+        // standard alphabet reversed
+        let symbols = "/+9876543210zyxwvutsrqponmlkjihgfedcbaZYXWVUTSRQPONMLKJIHGFEDCBA";
+        let engine = Engine::new(Alphabet::new(symbols).unwrap(), Config::new());
+
+        assert_eq!(symbols, engine.alphabet().as_str());
+        assert_eq!("//==", engine.encode(&[0]));
+        assert_eq!(
+            Vec::<u8>::from("Hello, World!"),
+            engine.decode(&engine.encode(b"Hello, World!")).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_invalid_custom_alphabet() {
+        assert_eq!(Alphabet::new("abc"), Err(AlphabetError::InvalidLength(3)));
+        assert_eq!(
+            Alphabet::new("AACDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/"),
+            Err(AlphabetError::DuplicateSymbol('A'))
+        );
+        assert_eq!(
+            Alphabet::new("=BCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/"),
+            Err(AlphabetError::InvalidSymbol('='))
+        );
+        assert_eq!(
+            Alphabet::new(" BCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/"),
+            Err(AlphabetError::InvalidSymbol(' '))
+        );
+    }
+
+    #[test]
+    fn test_padding_modes() {
+        let optional = Engine::new(
+            "standard".parse().unwrap(),
+            Config::new().with_padding(PaddingMode::Optional),
+        );
+
+        assert_eq!("YQ==", optional.encode(b"a"));
+        assert_eq!("YQ", STANDARD_NO_PAD.encode(b"a"));
+        assert_eq!("YWI", STANDARD_NO_PAD.encode(b"ab"));
+
+        // required
+        assert_eq!(
+            STANDARD.decode("YQ"),
+            Err(DecodeError::InvalidLength { offset: 0 })
+        );
+
+        // optional
+        assert_eq!(vec![0x61_u8], optional.decode("YQ==").unwrap());
+        assert_eq!(vec![0x61_u8], optional.decode("YQ").unwrap());
+        assert_eq!(
+            optional.decode("YQ="),
+            Err(DecodeError::InvalidLength { offset: 0 })
+        );
+
+        // forbidden
+        assert_eq!(vec![0x61_u8, 0x62], STANDARD_NO_PAD.decode("YWI").unwrap());
+        assert_eq!(
+            STANDARD_NO_PAD.decode("YWI="),
+            Err(DecodeError::InvalidPadding { offset: 3 })
+        );
+
+        // a lone symbol in the last group can't hold a byte in any mode
+        assert_eq!(
+            STANDARD_NO_PAD.decode("YWFhY"),
+            Err(DecodeError::InvalidLength { offset: 4 })
+        );
+    }
+
+    #[test]
+    fn test_line_wrap_encode() {
+        let data = pseudo_random_bytes(7, 100);
+        let single = STANDARD.encode(&data);
+
+        let mime = MIME.encode(&data);
+        let lines = mime.split("\r\n").collect::<Vec<&str>>();
+        assert_eq!(
+            vec![76, 60],
+            lines.iter().map(|x| x.len()).collect::<Vec<usize>>()
+        );
+        assert_eq!(single, lines.concat());
+
+        let pem = PEM.encode(&data);
+        assert!(!pem.contains('\r'));
+        assert_eq!(
+            vec![64, 64, 8],
+            pem.lines().map(|x| x.len()).collect::<Vec<usize>>()
+        );
+
+        // exact multiple of the width doesn't leave a trailing line ending
+        let even = Engine::new(
+            STANDARD_ALPHABET,
+            Config::new().with_line_wrap(4, LineEnding::Lf),
+        );
+        assert_eq!("Zm9v\nYmFy", even.encode(b"foobar"));
+    }
+
+    #[test]
+    fn test_lenient_decode() {
+        let data = pseudo_random_bytes(11, 200);
+
+        assert_eq!(data, MIME.decode(&MIME.encode(&data)).unwrap());
+        assert_eq!(data, PEM.decode(&PEM.encode(&data)).unwrap());
+        assert_eq!(
+            Vec::<u8>::from("Hello, World!"),
+            PEM.decode("  SGVsbG8s\r\n\tIFdvcmxk\nIQ=\n=\n").unwrap()
+        );
+
+        // whitespace is only skipped when asked
+        assert_eq!(
+            STANDARD.decode("SGVs\nbG8s"),
+            Err(DecodeError::InvalidSymbol {
+                offset: 4,
+                symbol: '\n'
+            })
+        );
+    }
+
+    #[test]
+    fn test_lenient_decode_error_offsets() {
+        // offsets point into the original input, not the whitespace free copy
+        assert_eq!(
+            PEM.decode("SGVs\r\nb!8s"),
+            Err(DecodeError::InvalidSymbol {
+                offset: 7,
+                symbol: '!'
+            })
+        );
+        assert_eq!(
+            PEM.decode("SGVs\nbG8\n"),
+            Err(DecodeError::InvalidLength { offset: 5 })
+        );
+        assert_eq!(
+            PEM.decode("YQ\n=\n=\n="),
+            Err(DecodeError::InvalidPadding { offset: 3 })
+        );
+    }
+
+    // Hands out the data a few bytes at a time to exercise partial reads.
+    struct TrickleReader<'a> {
+        data: &'a [u8],
+        step: usize,
+    }
+
+    impl Read for TrickleReader<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let count = self.step.min(buf.len()).min(self.data.len());
+            buf[..count].copy_from_slice(&self.data[..count]);
+            self.data = &self.data[count..];
+            Ok(count)
+        }
+    }
+
+    #[test]
+    fn test_encoder_writer_partial_writes() {
+        let data = pseudo_random_bytes(3, 1000);
+
+        for engine in [STANDARD, STANDARD_NO_PAD, MIME, PEM] {
+            for step in [1, 2, 3, 4, 5, 7, 64, 1000] {
+                let mut writer = EncoderWriter::new(Vec::new(), engine);
+                for chunk in data.chunks(step) {
+                    writer.write_all(chunk).unwrap();
+                }
+                let output = writer.finish().unwrap();
+
+                assert_eq!(engine.encode(&data).into_bytes(), output, "step {step}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_encoder_writer_finishes_on_drop() {
+        let mut output = Vec::new();
+        {
+            let mut writer = EncoderWriter::new(&mut output, STANDARD);
+            writer.write_all(b"fo").unwrap();
+        }
+
+        assert_eq!(b"Zm8=".to_vec(), output);
+    }
+
+    #[test]
+    fn test_decoder_reader_partial_reads() {
+        let data = pseudo_random_bytes(5, 20_000);
+
+        for engine in [STANDARD, URL_SAFE_NO_PAD, MIME, PEM] {
+            let encoded = engine.encode(&data);
+
+            for step in [1, 3, 4, 5, 77, 10_000] {
+                let mut reader = DecoderReader::new(
+                    TrickleReader {
+                        data: encoded.as_bytes(),
+                        step,
+                    },
+                    engine,
+                );
+
+                let mut output = Vec::new();
+                reader.read_to_end(&mut output).unwrap();
+
+                assert_eq!(data, output, "step {step}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_decoder_reader_errors() {
+        let mut reader = DecoderReader::new("SGVsbG8s\nIFd!cmxk".as_bytes(), PEM);
+        let error = reader.read_to_end(&mut Vec::new()).unwrap_err();
+
+        assert_eq!(io::ErrorKind::InvalidData, error.kind());
+        assert_eq!(
+            Some(&DecodeError::InvalidSymbol {
+                offset: 12,
+                symbol: '!'
+            }),
+            error.get_ref().unwrap().downcast_ref::<DecodeError>()
+        );
+
+        let mut reader = DecoderReader::new("SGVsbG8".as_bytes(), STANDARD);
+        let error = reader.read_to_end(&mut Vec::new()).unwrap_err();
+        assert_eq!(
+            Some(&DecodeError::InvalidLength { offset: 4 }),
+            error.get_ref().unwrap().downcast_ref::<DecodeError>()
+        );
+    }
+
+    #[test]
+    fn test_trailing_whitespace_decode() {
+        // only lenient mode skips whitespace, even the newline ending a file
+        assert_eq!(
+            decode("YQ==\n"),
+            Err(DecodeError::InvalidSymbol {
+                offset: 4,
+                symbol: '\n'
+            })
+        );
+        assert_eq!(vec![0x61_u8], PEM.decode("YQ==\n").unwrap());
+
+        let mut reader = DecoderReader::new("YQ==\r\n".as_bytes(), STANDARD);
+        let error = reader.read_to_end(&mut Vec::new()).unwrap_err();
+        assert_eq!(
+            Some(&DecodeError::InvalidSymbol {
+                offset: 4,
+                symbol: '\r'
+            }),
+            error.get_ref().unwrap().downcast_ref::<DecodeError>()
+        );
+    }
 }
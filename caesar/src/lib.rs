@@ -1,45 +1,499 @@
 pub mod caesar {
+    use std::collections::HashMap;
+    use std::fmt;
+    use std::str::FromStr;
 
-    pub fn brute_force(input: &str) -> Vec<String> {
-        let mut collect: Vec<String> = Vec::with_capacity(26);
+    // Characters rotated among themselves, as one or more cycles that each
+    // rotate on their own, e.g. lowercase and uppercase letters so case is
+    // kept. Anything outside every cycle is left alone.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct Alphabet {
+        cycles: Vec<Vec<char>>,
+        // cycle and position of every character
+        positions: HashMap<char, (usize, usize)>,
+    }
 
-        for i in 0..26 {
-            collect.push(rotate_string(input.to_string(), i));
+    impl Alphabet {
+        // Cycles of any characters, each at least one long with no character
+        // in two places.
+        pub fn new(cycles: Vec<Vec<char>>) -> Result<Alphabet, AlphabetError> {
+            if cycles.is_empty() || cycles.iter().any(|x| x.is_empty()) {
+                return Err(AlphabetError::Empty);
+            }
+
+            let mut positions = HashMap::new();
+            for (cycle, chars) in cycles.iter().enumerate() {
+                for (position, c) in chars.iter().enumerate() {
+                    if positions.insert(*c, (cycle, position)).is_some() {
+                        return Err(AlphabetError::DuplicateSymbol(*c));
+                    }
+                }
+            }
+
+            Ok(Alphabet { cycles, positions })
         }
 
-        collect
-    }
+        // Letters in order, along with their other case as a second cycle when
+        // every one of them has a single character other case that isn't
+        // already a symbol, so "abc" also turns "ABC".
+        pub fn custom(symbols: &str) -> Result<Alphabet, AlphabetError> {
+            let symbols = symbols.chars().collect::<Vec<char>>();
 
-    pub fn rotate_string(input: String, index: u8) -> String {
-        if index > 0 {
+            let other_case = symbols
+                .iter()
+                .map(|x| {
+                    let mut other = match x.is_lowercase() {
+                        true => x.to_uppercase().collect::<Vec<char>>(),
+                        false => x.to_lowercase().collect::<Vec<char>>(),
+                    };
+                    match (other.pop(), other.is_empty()) {
+                        (Some(other), true) if other != *x && !symbols.contains(&other) => {
+                            Some(other)
+                        }
+                        _ => None,
+                    }
+                })
+                .collect::<Option<Vec<char>>>();
+
+            match other_case {
+                Some(other_case) => Alphabet::new(vec![symbols, other_case]),
+                None => Alphabet::new(vec![symbols]),
+            }
+        }
+
+        // Presets, which are known to be valid.
+        fn preset(cycles: Vec<Vec<char>>) -> Alphabet {
+            Alphabet::new(cycles).unwrap()
+        }
+
+        fn cased(lowercase: &str) -> Vec<Vec<char>> {
+            vec![
+                lowercase.chars().collect(),
+                lowercase.to_uppercase().chars().collect(),
+            ]
+        }
+
+        // a to z and A to Z, what rotate_string works on.
+        pub fn latin() -> Alphabet {
+            Alphabet::preset(Alphabet::cased(LATIN))
+        }
+
+        // The 33 letters of the Russian alphabet, ё after е.
+        pub fn cyrillic() -> Alphabet {
+            Alphabet::preset(Alphabet::cased(CYRILLIC))
+        }
+
+        // The 24 letters of the Greek alphabet, a final ς is left as it is.
+        pub fn greek() -> Alphabet {
+            Alphabet::preset(Alphabet::cased(GREEK))
+        }
+
+        pub fn digits() -> Alphabet {
+            Alphabet::preset(vec![('0'..='9').collect()])
+        }
+
+        // Latin letters and digits, each rotating on their own.
+        pub fn alphanumeric() -> Alphabet {
+            let mut cycles = Alphabet::cased(LATIN);
+            cycles.push(('0'..='9').collect());
+
+            Alphabet::preset(cycles)
+        }
+
+        // Every printable ASCII character bar space, 33 to 126, as ROT47 uses.
+        pub fn printable() -> Alphabet {
+            Alphabet::preset(vec![('!'..='~').collect()])
+        }
+
+        pub fn contains(&self, c: char) -> bool {
+            self.positions.contains_key(&c)
+        }
+
+        // Shifts before every rotation repeats, 26 for latin. Rotating by
+        // shift and then by period - shift gets back where it started.
+        pub fn period(&self) -> usize {
+            self.cycles.iter().map(|x| x.len()).fold(1, lcm)
+        }
+
+        // Every character in the alphabet moved shift places along its cycle.
+        pub fn rotate(&self, input: &str, shift: usize) -> String {
+            self.translate(self, input, shift)
+        }
+
+        // Each cycle reordered to start with the keyword's characters, with
+        // repeats and characters outside the cycle dropped, then the rest in
+        // order. Case is ignored, so "SECRET" mixes latin into
+        // "secrtabdfg..." and "SECRTABDFG...". A keyword with characters but
+        // none of them symbols would mix nothing, so it's an error.
+        pub fn mixed(&self, keyword: &str) -> Result<Alphabet, AlphabetError> {
+            let mut used = false;
+            let cycles = self
+                .cycles
+                .iter()
+                .enumerate()
+                .map(|(i, cycle)| {
+                    let mut mixed = Vec::with_capacity(cycle.len());
+                    for c in keyword.chars() {
+                        let position = std::iter::once(c)
+                            .chain(c.to_lowercase())
+                            .chain(c.to_uppercase())
+                            .find_map(|x| match self.positions.get(&x) {
+                                Some((cycle, position)) if *cycle == i => Some(*position),
+                                _ => None,
+                            });
+                        if let Some(position) = position {
+                            used = true;
+                            if !mixed.contains(&cycle[position]) {
+                                mixed.push(cycle[position]);
+                            }
+                        }
+                    }
+
+                    let rest = cycle
+                        .iter()
+                        .filter(|x| !mixed.contains(x))
+                        .copied()
+                        .collect::<Vec<char>>();
+                    mixed.extend(rest);
+
+                    mixed
+                })
+                .collect();
+
+            match used || keyword.is_empty() {
+                true => Ok(Alphabet::preset(cycles)),
+                false => Err(AlphabetError::UnusableKeyword(keyword.to_string())),
+            }
+        }
+
+        // Each character's place in its cycle moved shift along and read from
+        // the same cycle of an alphabet of the same shape.
+        fn translate(&self, to: &Alphabet, input: &str, shift: usize) -> String {
             input
                 .chars()
-                .map(|x: char| {
-                    if x.is_uppercase() {
-                        (0x41 + (((x as u8 - 0x41) + index) % 26)) as char
-                    } else if x.is_lowercase() {
-                        (0x61 + (((x as u8 - 0x61) + index) % 26)) as char
-                    } else {
-                        x
+                .map(|x| match self.positions.get(&x) {
+                    Some((cycle, position)) => {
+                        let cycle = &to.cycles[*cycle];
+                        cycle[(position + shift % cycle.len()) % cycle.len()]
                     }
+                    None => x,
                 })
-                .collect::<String>()
-        } else {
-            input
+                .collect()
+        }
+
+        // Every distinct rotation, unrotated first.
+        pub fn brute_force(&self, input: &str) -> Vec<String> {
+            (0..self.period()).map(|x| self.rotate(input, x)).collect()
+        }
+    }
+
+    const LATIN: &str = "abcdefghijklmnopqrstuvwxyz";
+    const CYRILLIC: &str = "абвгдеёжзийклмнопрстуфхцчшщъыьэюя";
+    const GREEK: &str = "αβγδεζηθικλμνξοπρστυφχψω";
+
+    // Accepts the name of a built in alphabet or the symbols of a custom one.
+    impl FromStr for Alphabet {
+        type Err = AlphabetError;
+
+        fn from_str(name: &str) -> Result<Alphabet, AlphabetError> {
+            match name {
+                "latin" | "letters" => Ok(Alphabet::latin()),
+                "cyrillic" => Ok(Alphabet::cyrillic()),
+                "greek" => Ok(Alphabet::greek()),
+                "digits" => Ok(Alphabet::digits()),
+                "alphanumeric" => Ok(Alphabet::alphanumeric()),
+                "printable" => Ok(Alphabet::printable()),
+                custom => Alphabet::custom(custom),
+            }
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub enum AlphabetError {
+        // No symbols at all, or a cycle without any.
+        Empty,
+        DuplicateSymbol(char),
+        // A keyword without any of the alphabet's symbols.
+        UnusableKeyword(String),
+    }
+
+    impl fmt::Display for AlphabetError {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            match self {
+                AlphabetError::Empty => write!(
+                    f,
+                    "caesar alphabet has no symbols (or use latin, cyrillic, greek, digits, alphanumeric, printable)"
+                ),
+                AlphabetError::DuplicateSymbol(symbol) => {
+                    write!(f, "caesar alphabet has duplicate symbol {symbol:?}")
+                }
+                AlphabetError::UnusableKeyword(keyword) => {
+                    write!(f, "keyword {keyword:?} has none of the caesar alphabet's symbols")
+                }
+            }
+        }
+    }
+
+    impl std::error::Error for AlphabetError {}
+
+    // Keyed caesar, shifting onto a keyword mixed alphabet rather than the
+    // alphabet itself. An empty keyword is plain caesar.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct Keyed {
+        plain: Alphabet,
+        mixed: Alphabet,
+    }
+
+    impl Keyed {
+        pub fn new(alphabet: &Alphabet, keyword: &str) -> Result<Keyed, AlphabetError> {
+            Ok(Keyed {
+                plain: alphabet.clone(),
+                mixed: alphabet.mixed(keyword)?,
+            })
         }
+
+        pub fn mixed(&self) -> &Alphabet {
+            &self.mixed
+        }
+
+        pub fn encrypt(&self, input: &str, shift: usize) -> String {
+            self.plain.translate(&self.mixed, input, shift)
+        }
+
+        pub fn decrypt(&self, input: &str, shift: usize) -> String {
+            let period = self.plain.period();
+            self.mixed
+                .translate(&self.plain, input, period - shift % period)
+        }
+
+        // Decryption with every distinct shift, shift 0 first.
+        pub fn brute_force(&self, input: &str) -> Vec<String> {
+            (0..self.plain.period())
+                .map(|x| self.decrypt(input, x))
+                .collect()
+        }
+    }
+
+    // A keyed caesar decryption found with a keyword from a list.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct KeyedCandidate {
+        pub keyword: String,
+        pub shift: usize,
+        pub plaintext: String,
+    }
+
+    // Every shift of every keyword, keywords mixing the alphabet the same way
+    // as an earlier one, like "secrets" after "secret", or without any of its
+    // symbols are skipped.
+    pub fn brute_force_keywords<'a>(
+        alphabet: &Alphabet,
+        input: &str,
+        keywords: impl IntoIterator<Item = &'a str>,
+    ) -> Vec<KeyedCandidate> {
+        let mut seen = Vec::new();
+        let mut candidates = Vec::new();
+
+        for keyword in keywords {
+            let keyed = match Keyed::new(alphabet, keyword) {
+                Ok(keyed) if !seen.contains(keyed.mixed()) => keyed,
+                _ => continue,
+            };
+
+            for (shift, plaintext) in keyed.brute_force(input).into_iter().enumerate() {
+                candidates.push(KeyedCandidate {
+                    keyword: keyword.to_string(),
+                    shift,
+                    plaintext,
+                });
+            }
+            seen.push(keyed.mixed);
+        }
+
+        candidates
+    }
+
+    fn lcm(a: usize, b: usize) -> usize {
+        let gcd = |mut a: usize, mut b: usize| {
+            while b > 0 {
+                (a, b) = (b, a % b);
+            }
+            a
+        };
+
+        a / gcd(a, b) * b
+    }
+
+    // Printable ASCII rotated half way round, its own inverse.
+    pub fn rot47(input: &str) -> String {
+        Alphabet::printable().rotate(input, 47)
+    }
+
+    // Digits rotated half way round.
+    pub fn rot5(input: &str) -> String {
+        Alphabet::digits().rotate(input, 5)
+    }
+
+    // ROT13 on the letters and ROT5 on the digits.
+    pub fn rot18(input: &str) -> String {
+        rot5(&Alphabet::latin().rotate(input, 13))
+    }
+
+    pub fn brute_force(input: &str) -> Vec<String> {
+        Alphabet::latin().brute_force(input)
+    }
+
+    // Latin letters rotated by index, anything else including non ASCII
+    // letters left as it is.
+    pub fn rotate_string(input: String, index: u8) -> String {
+        Alphabet::latin().rotate(&input, index as usize)
+    }
+}
+
+// Affine cipher E(x) = ax + b mod 26 on latin letters, of which caesar is
+// the a = 1 case. Case is kept and anything else is left as it is.
+pub mod affine {
+    use std::fmt;
+
+    const M: u32 = 26;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct Key {
+        a: u32,
+        b: u32,
+        // a^-1 mod 26, what decryption multiplies by
+        inverse: u32,
+    }
+
+    impl Key {
+        // a must be coprime to 26 so decryption can undo it, b is any shift.
+        pub fn new(a: u32, b: u32) -> Result<Key, KeyError> {
+            match mod_inverse(a, M) {
+                Some(inverse) => Ok(Key {
+                    a: a % M,
+                    b: b % M,
+                    inverse,
+                }),
+                None => Err(KeyError::NotInvertible(a)),
+            }
+        }
+
+        // All 312 keys, 12 choices of a by 26 of b.
+        pub fn all() -> Vec<Key> {
+            (1..M)
+                .flat_map(|a| (0..M).map(move |b| Key::new(a, b)))
+                .filter_map(Result::ok)
+                .collect()
+        }
+
+        pub fn a(&self) -> u32 {
+            self.a
+        }
+
+        pub fn b(&self) -> u32 {
+            self.b
+        }
+
+        pub fn inverse(&self) -> u32 {
+            self.inverse
+        }
+    }
+
+    impl fmt::Display for Key {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "a={} b={}", self.a, self.b)
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub enum KeyError {
+        // a shares a factor with 26, so several letters encrypt the same.
+        NotInvertible(u32),
+        // Known plaintext that isn't a latin letter.
+        NotALetter(char),
+    }
+
+    impl fmt::Display for KeyError {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            match self {
+                KeyError::NotInvertible(a) => write!(
+                    f,
+                    "affine key a={a} has no inverse mod 26, it must be odd and not a multiple of 13"
+                ),
+                KeyError::NotALetter(c) => write!(f, "known plaintext {c:?} isn't a letter"),
+            }
+        }
+    }
+
+    impl std::error::Error for KeyError {}
+
+    // x such that ax = 1 mod m, if a and m are coprime.
+    pub fn mod_inverse(a: u32, m: u32) -> Option<u32> {
+        (1..m).find(|x| (a % m) * x % m == 1)
+    }
+
+    pub fn encrypt(input: &str, key: Key) -> String {
+        map_letters(input, |x| (key.a * x + key.b) % M)
+    }
+
+    pub fn decrypt(input: &str, key: Key) -> String {
+        map_letters(input, |x| key.inverse * (x + M - key.b) % M)
+    }
+
+    // Decryption with every key, in the order of Key::all.
+    pub fn brute_force(input: &str) -> Vec<(Key, String)> {
+        Key::all()
+            .into_iter()
+            .map(|key| (key, decrypt(input, key)))
+            .collect()
+    }
+
+    // Every key encrypting each plaintext letter to its ciphertext letter,
+    // ignoring case. Two pairs of plaintext letters leave at most one key
+    // unless the letters are 13 apart, which every a fits, and none at all
+    // means the pairs aren't from an affine cipher.
+    pub fn recover(pairs: &[(char, char)]) -> Result<Vec<Key>, KeyError> {
+        let pairs = pairs
+            .iter()
+            .map(|(plain, cipher)| Ok((index(*plain)?, index(*cipher)?)))
+            .collect::<Result<Vec<(u32, u32)>, KeyError>>()?;
+
+        Ok(Key::all()
+            .into_iter()
+            .filter(|key| pairs.iter().all(|(x, y)| (key.a * x + key.b) % M == *y))
+            .collect())
+    }
+
+    fn index(letter: char) -> Result<u32, KeyError> {
+        match letter.is_ascii_alphabetic() {
+            true => Ok((letter.to_ascii_lowercase() as u8 - b'a') as u32),
+            false => Err(KeyError::NotALetter(letter)),
+        }
+    }
+
+    fn map_letters(input: &str, f: impl Fn(u32) -> u32) -> String {
+        input
+            .chars()
+            .map(|x| match x {
+                'a'..='z' => (b'a' + f((x as u8 - b'a') as u32) as u8) as char,
+                'A'..='Z' => (b'A' + f((x as u8 - b'A') as u32) as u8) as char,
+                _ => x,
+            })
+            .collect()
     }
 }
 
 #[cfg(test)]
 mod tests {
 
+    use crate::affine::{self, Key, KeyError};
     use crate::caesar::*;
+    use proptest::prelude::*;
     #[test]
     fn rotate_single_char() {
         let lower = ["a", "b", "c", "d", "e", "f", "g"];
         let upper = ["A", "B", "C", "D", "E", "F", "G"];
 
-        for i in 0..7 as usize {
+        for i in 0..7_usize {
             assert_eq!(rotate_string("a".to_string(), i as u8), lower[i]);
 
             assert_eq!(rotate_string("A".to_string(), i as u8), upper[i]);
@@ -139,9 +593,253 @@ mod tests {
         test_set
             .iter()
             .zip(outcome)
-            .filter_map(|x| if *x.0 == x.1 { Some(true) } else { Some(false) })
+            .map(|x| *x.0 == x.1)
             .collect::<Vec<bool>>()
             .iter()
-            .for_each(|x| assert_eq!(*x, true));
+            .for_each(|x| assert!(*x));
+    }
+
+    #[test]
+    fn rotate_over_alphabets() {
+        assert_eq!("joqvu", Alphabet::latin().rotate("input", 1));
+        assert_eq!("Abc 890", Alphabet::latin().rotate("Zab 890", 1));
+        assert_eq!("Zab 901", Alphabet::digits().rotate("Zab 890", 1));
+        assert_eq!("Abc 901", Alphabet::alphanumeric().rotate("Zab 890", 1));
+        assert_eq!("[bc 9:1", Alphabet::printable().rotate("Zab 890", 1));
+        assert_eq!("!", Alphabet::printable().rotate("~", 1));
+
+        let custom = Alphabet::new(vec![vec!['x', 'y', 'z']]).unwrap();
+        assert_eq!("yzx-a", custom.rotate("xyz-a", 1));
+    }
+
+    #[test]
+    fn rot_presets() {
+        assert_eq!("w6==@ (@C=5P", rot47("Hello World!"));
+        assert_eq!("Hello World!", rot47(&rot47("Hello World!")));
+        assert_eq!("Room 678", rot5("Room 123"));
+        assert_eq!("Ebbz 678", rot18("Room 123"));
+        assert_eq!("Room 123", rot18(&rot18("Room 123")));
+    }
+
+    #[test]
+    fn brute_force_over_alphabets() {
+        assert_eq!(brute_force("Hello"), Alphabet::latin().brute_force("Hello"));
+        assert_eq!(10, Alphabet::digits().brute_force("42").len());
+        assert_eq!(94, Alphabet::printable().brute_force("w6==@").len());
+        assert_eq!(130, Alphabet::alphanumeric().period());
+        assert!(Alphabet::printable()
+            .brute_force("w6==@ (@C=5P")
+            .contains(&"Hello World!".to_string()));
+    }
+
+    #[test]
+    fn rotate_non_ascii() {
+        // used to panic on the uppercase letters
+        assert_eq!("Éwê Ж", rotate_string("Éjê Ж".to_string(), 13));
+
+        assert_eq!("Бёж Яа", Alphabet::cyrillic().rotate("Аеё Юя", 1));
+        assert_eq!("Εμμς Ω", Alphabet::greek().rotate("Δλλς Ψ", 1));
+        assert_eq!(33, Alphabet::cyrillic().period());
+        assert_eq!(24, Alphabet::greek().period());
+    }
+
+    #[test]
+    fn custom_alphabets() {
+        let swedish = Alphabet::custom("abcdefghijklmnopqrstuvwxyzåäö").unwrap();
+        assert_eq!(29, swedish.period());
+        assert_eq!("Åäö åa", swedish.rotate("Zåä zö", 1));
+
+        // symbols without a case rotate as one cycle
+        let symbols = "01234+-".parse::<Alphabet>().unwrap();
+        assert_eq!("-0+", symbols.rotate("+-4", 1));
+
+        assert_eq!(Alphabet::greek(), "greek".parse().unwrap());
+        assert_eq!(Alphabet::latin(), "letters".parse().unwrap());
+        assert_eq!(Err(AlphabetError::Empty), "".parse::<Alphabet>());
+        assert_eq!(
+            Err(AlphabetError::DuplicateSymbol('a')),
+            "abca".parse::<Alphabet>()
+        );
+        assert_eq!(
+            Err(AlphabetError::DuplicateSymbol('b')),
+            Alphabet::new(vec![vec!['a', 'b'], vec!['b']])
+        );
+    }
+
+    #[test]
+    fn keyed() {
+        let keyed = Keyed::new(&Alphabet::latin(), "key").unwrap();
+
+        assert_eq!(
+            "keyabcdfghijlmnopqrstuvwxz",
+            keyed.encrypt("abcdefghijklmnopqrstuvwxyz", 0)
+        );
+        assert_eq!("FBJJN, Vnqja!", keyed.encrypt("HELLO, World!", 0));
+        assert_eq!("GCLLO", keyed.encrypt("HELLO", 1));
+        assert_eq!("HELLO", keyed.decrypt("GCLLO", 1));
+        assert_eq!("HELLO", keyed.brute_force("GCLLO")[1]);
+        assert_eq!(
+            Alphabet::latin().mixed("secret").unwrap(),
+            Alphabet::latin().mixed("SECRETS").unwrap()
+        );
+
+        // no keyword is plain caesar
+        let plain = Keyed::new(&Alphabet::latin(), "").unwrap();
+        assert_eq!(
+            rotate_string("Hello".to_string(), 3),
+            plain.encrypt("Hello", 3)
+        );
+        assert_eq!("Hello", plain.decrypt("Khoor", 3));
+
+        // a keyword has to have some symbol to mix
+        for keyword in ["123", "ÉÉ"] {
+            assert_eq!(
+                Err(AlphabetError::UnusableKeyword(keyword.to_string())),
+                Keyed::new(&Alphabet::latin(), keyword)
+            );
+        }
+        assert!(Keyed::new(&Alphabet::latin(), "1a").is_ok());
+        assert!(Keyed::new(&Alphabet::digits(), "123").is_ok());
+    }
+
+    #[test]
+    fn keyword_brute_force() {
+        let ciphertext = Keyed::new(&Alphabet::latin(), "zebra")
+            .unwrap()
+            .encrypt("attack at dawn", 4);
+        let candidates = brute_force_keywords(
+            &Alphabet::latin(),
+            &ciphertext,
+            ["zebra", "cat", "ZEBRA", "42"],
+        );
+
+        // ZEBRA mixes the same as zebra and 42 mixes nothing, so only two
+        // keywords are tried
+        assert_eq!(52, candidates.len());
+        assert!(candidates.contains(&KeyedCandidate {
+            keyword: "zebra".to_string(),
+            shift: 4,
+            plaintext: "attack at dawn".to_string(),
+        }));
+    }
+
+    #[test]
+    fn affine_keys() {
+        assert_eq!(312, Key::all().len());
+        assert_eq!(Some(21), affine::mod_inverse(5, 26));
+        assert_eq!(None, affine::mod_inverse(13, 26));
+        assert_eq!(21, Key::new(5, 8).unwrap().inverse());
+        assert_eq!(Err(KeyError::NotInvertible(2)), Key::new(2, 1));
+        assert_eq!(Err(KeyError::NotInvertible(13)), Key::new(13, 1));
+        assert_eq!("a=5 b=8", Key::new(31, 34).unwrap().to_string());
+    }
+
+    #[test]
+    fn affine_encrypt_decrypt() {
+        let key = Key::new(5, 8).unwrap();
+
+        assert_eq!("IHHWVC SWFRCP!", affine::encrypt("AFFINE CIPHER!", key));
+        assert_eq!("Affine cipher", affine::decrypt("Ihhwvc swfrcp", key));
+        assert_eq!("Éihhwvc", affine::encrypt("Éaffine", key));
+
+        // a = 1 is caesar
+        assert_eq!(
+            rotate_string("Hello".to_string(), 3),
+            affine::encrypt("Hello", Key::new(1, 3).unwrap())
+        );
+    }
+
+    #[test]
+    fn affine_brute_force_and_recover() {
+        let key = Key::new(7, 3).unwrap();
+        let ciphertext = affine::encrypt("meet me at noon", key);
+
+        assert!(affine::brute_force(&ciphertext).contains(&(key, "meet me at noon".to_string())));
+
+        // "meet" encrypts to "jffg"
+        assert_eq!(Ok(vec![key]), affine::recover(&[('m', 'j'), ('T', 'G')]));
+        assert_eq!(Ok(vec![key]), affine::recover(&[('e', 'f'), ('m', 'j')]));
+
+        // a and n are 13 apart, 13a is 13 for any odd a
+        let keys = affine::recover(&[('a', 'd'), ('n', 'q')]).unwrap();
+        assert_eq!(12, keys.len());
+        assert!(keys.contains(&key));
+
+        assert_eq!(
+            Err(KeyError::NotALetter('1')),
+            affine::recover(&[('1', 'a')])
+        );
+        assert_eq!(Ok(vec![]), affine::recover(&[('a', 'b'), ('a', 'c')]));
+    }
+
+    fn presets() -> Vec<Alphabet> {
+        vec![
+            Alphabet::latin(),
+            Alphabet::cyrillic(),
+            Alphabet::greek(),
+            Alphabet::digits(),
+            Alphabet::alphanumeric(),
+            Alphabet::printable(),
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn rotate_string_round_trips(input in "\\PC*", index in 0..26_u8) {
+            let rotated = rotate_string(input.clone(), index);
+
+            prop_assert_eq!(input, rotate_string(rotated, 26 - index));
+        }
+
+        #[test]
+        fn presets_round_trip(input in "\\PC*", shift in 0..1000_usize) {
+            for alphabet in presets() {
+                let rotated = alphabet.rotate(&input, shift);
+                let back = alphabet.period() - shift % alphabet.period();
+
+                prop_assert_eq!(&input, &alphabet.rotate(&rotated, back));
+            }
+        }
+
+        #[test]
+        fn custom_round_trips(symbols in "\\PC+", input in "\\PC*", shift in 0..100_usize) {
+            if let Ok(alphabet) = Alphabet::custom(&symbols) {
+                let rotated = alphabet.rotate(&input, shift);
+                let back = alphabet.period() - shift % alphabet.period();
+
+                prop_assert_eq!(&input, &alphabet.rotate(&rotated, back));
+            }
+        }
+
+        #[test]
+        fn keyed_round_trips(keyword in "\\PC*", input in "\\PC*", shift in 0..1000_usize) {
+            for alphabet in presets() {
+                if let Ok(keyed) = Keyed::new(&alphabet, &keyword) {
+                    prop_assert_eq!(&input, &keyed.decrypt(&keyed.encrypt(&input, shift), shift));
+                }
+            }
+        }
+
+        #[test]
+        fn affine_round_trips(input in "\\PC*", key in 0..312_usize) {
+            let key = Key::all()[key];
+
+            prop_assert_eq!(&input, &affine::decrypt(&affine::encrypt(&input, key), key));
+        }
+
+        #[test]
+        fn outside_the_alphabet_is_untouched(input in "\\PC*", shift in 0..100_usize) {
+            for alphabet in presets() {
+                let rotated = alphabet.rotate(&input, shift);
+
+                prop_assert_eq!(input.chars().count(), rotated.chars().count());
+                for (before, after) in input.chars().zip(rotated.chars()) {
+                    prop_assert_eq!(alphabet.contains(before), alphabet.contains(after));
+                    if !alphabet.contains(before) {
+                        prop_assert_eq!(before, after);
+                    }
+                }
+            }
+        }
     }
 }
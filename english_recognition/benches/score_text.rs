@@ -0,0 +1,62 @@
+// Compares score_text against the quadratic version it replaced, on a single
+// candidate of growing length and on a full caesar brute force.
+use caesar::caesar::brute_force;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use english_recognition::frequency_analysis::{score_strings, score_text};
+use std::collections::HashMap;
+use std::hint::black_box;
+
+// score_text as it was, rebuilding the table and rescanning the candidate for
+// every character.
+fn score_text_quadratic(candidate: &str) -> f64 {
+    let letter_freq = ('a'..='z')
+        .zip(english_recognition::frequency_analysis::ENGLISH_FREQUENCIES)
+        .collect::<HashMap<char, f64>>();
+
+    let population: HashMap<char, f64> = candidate
+        .chars()
+        .map(|c| (c.to_ascii_lowercase(), candidate.matches(c).count() as f64))
+        .collect();
+
+    population
+        .iter()
+        .map(|(x, y)| match letter_freq.get(x) {
+            Some(n) => ((n * *y) / (candidate.len() as f64)).sqrt(),
+            None => 0.0,
+        })
+        .sum()
+}
+
+fn book() -> String {
+    std::fs::read_to_string("pride_prejudice.txt").expect("failed to open ./pride_prejudice.txt")
+}
+
+fn bench_score_text(c: &mut Criterion) {
+    let book = book();
+    let mut group = c.benchmark_group("score_text");
+
+    for length in [64, 512, book.len()] {
+        let candidate = &book[..length];
+        group.throughput(Throughput::Bytes(length as u64));
+
+        group.bench_with_input(BenchmarkId::new("linear", length), candidate, |b, x| {
+            b.iter(|| score_text(black_box(x)))
+        });
+        group.bench_with_input(BenchmarkId::new("quadratic", length), candidate, |b, x| {
+            b.iter(|| score_text_quadratic(black_box(x)))
+        });
+    }
+
+    group.finish();
+}
+
+fn bench_caesar_candidates(c: &mut Criterion) {
+    let candidates = brute_force(&book()[..512]);
+
+    c.bench_function("score_strings caesar 512", |b| {
+        b.iter(|| score_strings(black_box(candidates.clone())))
+    });
+}
+
+criterion_group!(benches, bench_score_text, bench_caesar_candidates);
+criterion_main!(benches);
@@ -0,0 +1,193 @@
+// Byte level model of plaintext covering all 256 byte values, so candidates
+// like XOR brute force output can be scored as they are without a lossy UTF-8
+// conversion. Spaces and punctuation count towards a good score and control or
+// high bytes sink it, which letter based scores can't do.
+use std::sync::OnceLock;
+
+use crate::frequency_analysis::ENGLISH_FREQUENCIES;
+use crate::scorer::Scorer;
+
+// Share of english text made up of each kind of byte, roughly as in prose.
+const SPACE: f64 = 0.165;
+const LOWERCASE: f64 = 0.72;
+const UPPERCASE: f64 = 0.03;
+const DIGITS: f64 = 0.005;
+const PUNCTUATION: [(u8, f64); 16] = [
+    (b'\n', 0.015),
+    (b',', 0.012),
+    (b'.', 0.011),
+    (b'\'', 0.003),
+    (b'"', 0.003),
+    (b'-', 0.002),
+    (b'\r', 0.0005),
+    (b'\t', 0.0005),
+    (b';', 0.0005),
+    (b':', 0.0005),
+    (b'!', 0.0005),
+    (b'?', 0.0005),
+    (b'(', 0.0003),
+    (b')', 0.0003),
+    (b'_', 0.0002),
+    (b'/', 0.0002),
+];
+// Any other printable ASCII byte, a byte of UTF-8 beyond ASCII and a control
+// byte.
+const OTHER_PRINTABLE: f64 = 1e-4;
+const HIGH: f64 = 1e-5;
+const CONTROL: f64 = 1e-7;
+
+// Sightings added to every byte value when training so none is impossible.
+const SMOOTHING: f64 = 0.5;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ByteModel {
+    // log10 probability of each byte value
+    log_probs: [f64; 256],
+}
+
+impl ByteModel {
+    // Model from a relative weight for every byte value, they needn't sum to
+    // 1 but must all be above 0.
+    pub fn from_weights(weights: &[f64; 256]) -> ByteModel {
+        let total = weights.iter().sum::<f64>();
+
+        ByteModel {
+            log_probs: weights.map(|x| (x / total).log10()),
+        }
+    }
+
+    // Model of how often each byte turns up in a sample.
+    pub fn train(sample: &[u8]) -> ByteModel {
+        let mut weights = [SMOOTHING; 256];
+        for byte in sample {
+            weights[*byte as usize] += 1.0;
+        }
+
+        ByteModel::from_weights(&weights)
+    }
+
+    pub fn log_probability(&self, byte: u8) -> f64 {
+        self.log_probs[byte as usize]
+    }
+
+    // Average log10 probability per byte, closer to zero is more likely.
+    // Nothing at all scores as badly as the least likely byte.
+    pub fn score(&self, bytes: &[u8]) -> f64 {
+        match bytes.len() {
+            0 => self.log_probs.iter().fold(f64::INFINITY, |a, b| a.min(*b)),
+            length => bytes.iter().map(|x| self.log_probability(*x)).sum::<f64>() / length as f64,
+        }
+    }
+}
+
+impl Scorer for ByteModel {
+    fn score(&self, candidate: &str) -> f64 {
+        ByteModel::score(self, candidate.as_bytes())
+    }
+
+    fn score_bytes(&self, candidate: &[u8]) -> f64 {
+        ByteModel::score(self, candidate)
+    }
+}
+
+pub fn english() -> &'static ByteModel {
+    static MODEL: OnceLock<ByteModel> = OnceLock::new();
+    MODEL.get_or_init(|| ByteModel::from_weights(&english_weights()))
+}
+
+fn english_weights() -> [f64; 256] {
+    let mut weights = [0.0; 256];
+
+    for (byte, weight) in weights.iter_mut().enumerate() {
+        *weight = match byte as u8 {
+            0x20..=0x7e => OTHER_PRINTABLE,
+            0x80..=0xff => HIGH,
+            _ => CONTROL,
+        };
+    }
+
+    for (letter, frequency) in ENGLISH_FREQUENCIES.iter().enumerate() {
+        weights[b'a' as usize + letter] = LOWERCASE * frequency;
+        weights[b'A' as usize + letter] = UPPERCASE * frequency;
+    }
+    for digit in b'0'..=b'9' {
+        weights[digit as usize] = DIGITS / 10.0;
+    }
+    for (byte, share) in PUNCTUATION {
+        weights[byte as usize] = share;
+    }
+    weights[b' ' as usize] = SPACE;
+
+    weights
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bytes::*;
+
+    const ENGLISH_TEXT: &str = "It is a truth universally acknowledged, that a single man in possession of a good fortune, must be in want of a wife.";
+
+    fn xor(bytes: &[u8], key: u8) -> Vec<u8> {
+        bytes.iter().map(|x| x ^ key).collect()
+    }
+
+    #[test]
+    fn test_english_distribution() {
+        let model = english();
+        let total = (0..=255)
+            .map(|x| 10_f64.powf(model.log_probability(x)))
+            .sum::<f64>();
+
+        assert!((total - 1.0).abs() < 1e-9);
+        assert!(model.log_probability(b' ') > model.log_probability(b'e'));
+        assert!(model.log_probability(b'e') > model.log_probability(b'E'));
+        assert!(model.log_probability(b'~') > model.log_probability(0xc3));
+        assert!(model.log_probability(0xc3) > model.log_probability(0x00));
+    }
+
+    #[test]
+    fn test_xor_key_selection() {
+        let ciphertext = xor(ENGLISH_TEXT.as_bytes(), 0x5a);
+
+        let best = (0..=255)
+            .max_by(|a, b| {
+                let a = english().score(&xor(&ciphertext, *a));
+                let b = english().score(&xor(&ciphertext, *b));
+                a.total_cmp(&b)
+            })
+            .unwrap();
+
+        // 0x7a would only swap the case of every letter and make spaces nulls
+        assert_eq!(0x5a, best);
+    }
+
+    #[test]
+    fn test_binary_and_invalid_utf8() {
+        let random = (0..=255).collect::<Vec<u8>>();
+
+        assert!(english().score(ENGLISH_TEXT.as_bytes()) > english().score(&random));
+        assert!(english().score(&[0xff, 0xfe, b'h', b'i']).is_finite());
+        assert_eq!(english().log_probability(0x00), english().score(b""));
+
+        // as a Scorer it sees the bytes, not a conversion with replacements
+        assert_eq!(
+            english().score(&[0xff]),
+            Scorer::score_bytes(english(), &[0xff])
+        );
+        assert_eq!(
+            english().score(ENGLISH_TEXT.as_bytes()),
+            Scorer::score(english(), ENGLISH_TEXT)
+        );
+    }
+
+    #[test]
+    fn test_train() {
+        let model = ByteModel::train(b"aab");
+
+        // 2.5, 1.5 and 0.5 of 131 sightings
+        assert_eq!((2.5_f64 / 131.0).log10(), model.log_probability(b'a'));
+        assert_eq!((1.5_f64 / 131.0).log10(), model.log_probability(b'b'));
+        assert_eq!((0.5_f64 / 131.0).log10(), model.log_probability(0));
+        assert!(model.score(b"ab") > model.score(b"xy"));
+    }
+}
@@ -0,0 +1,310 @@
+// Registry of the languages candidates can be scored against, each with its
+// letter frequencies, most common words and an n-gram model. Accented letters
+// count as the letter they're built on so e.g. é adds to e.
+use std::sync::OnceLock;
+
+use crate::frequency_analysis::{bhattacharyya, ENGLISH_FREQUENCIES};
+use crate::ngram::NgramModel;
+use crate::scorer::descending;
+
+pub struct Language {
+    pub code: &'static str,
+    pub name: &'static str,
+    // a to z, accented letters folded in
+    pub frequencies: [f64; 26],
+    pub common_words: &'static [&'static str],
+    // embedded NgramModel::to_bytes, loaded on first use
+    ngrams: &'static [u8],
+    model: OnceLock<NgramModel>,
+}
+
+impl Language {
+    pub fn ngram_model(&self) -> &NgramModel {
+        self.model.get_or_init(|| {
+            NgramModel::from_bytes(self.ngrams).expect("embedded n-gram model is valid")
+        })
+    }
+
+    // Index of coincidence expected of text in the language, the chance two
+    // letters picked from it are the same.
+    pub fn index_of_coincidence(&self) -> f64 {
+        self.frequencies.iter().map(|x| x * x).sum()
+    }
+
+    // Bhattacharyya coefficient against the language's letter frequencies, 1
+    // for a perfect match down to 0.
+    pub fn score(&self, candidate: &str) -> f64 {
+        bhattacharyya(candidate, &self.frequencies)
+    }
+}
+
+// N-gram models are trained from pride_prejudice.txt for english and
+// data/corpora for the others, regenerate with
+// UPDATE_NGRAMS=1 cargo test test_embedded_models
+pub static ENGLISH: Language = Language {
+    code: "en",
+    name: "english",
+    frequencies: ENGLISH_FREQUENCIES,
+    common_words: &[
+        "a", "about", "after", "all", "also", "an", "and", "any", "are", "as", "at", "be",
+        "because", "been", "but", "by", "can", "come", "could", "day", "do", "even", "first",
+        "for", "from", "get", "give", "go", "good", "had", "has", "have", "he", "her", "him",
+        "his", "how", "i", "if", "in", "into", "is", "it", "its", "just", "know", "like", "look",
+        "make", "man", "me", "more", "most", "my", "new", "no", "not", "now", "of", "on", "one",
+        "only", "or", "other", "our", "out", "over", "people", "said", "say", "see", "she", "so",
+        "some", "take", "than", "that", "the", "their", "them", "then", "there", "these", "they",
+        "think", "this", "time", "to", "two", "up", "us", "use", "very", "was", "way", "we",
+        "well", "were", "what", "when", "which", "who", "will", "with", "work", "would", "year",
+        "you", "your",
+    ],
+    ngrams: include_bytes!("../data/english_quadgrams.bin"),
+    model: OnceLock::new(),
+};
+
+pub static FRENCH: Language = Language {
+    code: "fr",
+    name: "french",
+    frequencies: [
+        0.08173, 0.00901, 0.03345, 0.03669, 0.16716, 0.01066, 0.00866, 0.00737, 0.07579, 0.00613,
+        0.00074, 0.05456, 0.02968, 0.07095, 0.05837, 0.02521, 0.01362, 0.06693, 0.07948, 0.07244,
+        0.06429, 0.01838, 0.00049, 0.00427, 0.00128, 0.00326,
+    ],
+    common_words: &[
+        "a", "au", "aux", "avec", "ce", "ces", "cette", "dans", "de", "des", "du", "elle", "en",
+        "est", "et", "il", "ils", "je", "la", "le", "les", "leur", "mais", "ne", "nous", "on",
+        "ou", "par", "pas", "plus", "pour", "qu", "que", "qui", "sa", "se", "son", "sont", "sur",
+        "tout", "un", "une", "vous", "y", "été", "être", "avoir", "fait", "comme", "bien",
+    ],
+    ngrams: include_bytes!("../data/french_quadgrams.bin"),
+    model: OnceLock::new(),
+};
+
+pub static GERMAN: Language = Language {
+    code: "de",
+    name: "german",
+    frequencies: [
+        0.07094, 0.01886, 0.02732, 0.05076, 0.16396, 0.01656, 0.03009, 0.04577, 0.06550, 0.00268,
+        0.01417, 0.03437, 0.02534, 0.09776, 0.03037, 0.00670, 0.00018, 0.07003, 0.07577, 0.06154,
+        0.05161, 0.00846, 0.01921, 0.00034, 0.00039, 0.01134,
+    ],
+    common_words: &[
+        "auf", "aus", "bei", "bis", "das", "dass", "dem", "den", "der", "des", "die", "ein",
+        "eine", "einem", "einen", "einer", "er", "es", "für", "hat", "ich", "im", "in", "ist",
+        "mit", "nach", "nicht", "noch", "nur", "oder", "sich", "sie", "sind", "so", "um", "und",
+        "von", "vor", "war", "werden", "wie", "wir", "wird", "zu", "zum", "zur", "über", "auch",
+        "als", "an",
+    ],
+    ngrams: include_bytes!("../data/german_quadgrams.bin"),
+    model: OnceLock::new(),
+};
+
+pub static SPANISH: Language = Language {
+    code: "es",
+    name: "spanish",
+    frequencies: [
+        0.12027, 0.02215, 0.04019, 0.05010, 0.12614, 0.00692, 0.01768, 0.00703, 0.06972, 0.00493,
+        0.00011, 0.04967, 0.03157, 0.07023, 0.09510, 0.02510, 0.00877, 0.06871, 0.07977, 0.04632,
+        0.03107, 0.01138, 0.00017, 0.00215, 0.01008, 0.00467,
+    ],
+    common_words: &[
+        "a", "al", "como", "con", "de", "del", "el", "en", "es", "esta", "está", "este", "ha",
+        "la", "las", "le", "lo", "los", "más", "me", "mi", "muy", "no", "o", "para", "pero", "por",
+        "porque", "que", "se", "si", "sin", "su", "sus", "también", "te", "un", "una", "uno", "y",
+        "ya", "yo", "era", "son", "hay", "todo", "cuando", "sobre", "entre", "fue",
+    ],
+    ngrams: include_bytes!("../data/spanish_quadgrams.bin"),
+    model: OnceLock::new(),
+};
+
+pub static DUTCH: Language = Language {
+    code: "nl",
+    name: "dutch",
+    frequencies: [
+        0.07486, 0.01584, 0.01242, 0.05933, 0.18910, 0.00805, 0.03403, 0.02380, 0.06499, 0.01460,
+        0.02248, 0.03568, 0.02213, 0.10032, 0.06063, 0.01570, 0.00009, 0.06411, 0.03730, 0.06790,
+        0.01990, 0.02850, 0.01520, 0.00036, 0.00035, 0.01390,
+    ],
+    common_words: &[
+        "aan", "al", "als", "bij", "dan", "dat", "de", "die", "dit", "door", "een", "en", "er",
+        "het", "hij", "hem", "hun", "ik", "in", "is", "je", "kan", "maar", "met", "na", "naar",
+        "niet", "nog", "nu", "of", "om", "ook", "op", "over", "te", "tot", "uit", "van", "voor",
+        "was", "wat", "we", "werd", "wordt", "ze", "zijn", "zich", "zo", "worden", "zal",
+    ],
+    ngrams: include_bytes!("../data/dutch_quadgrams.bin"),
+    model: OnceLock::new(),
+};
+
+pub static LANGUAGES: [&Language; 5] = [&ENGLISH, &FRENCH, &GERMAN, &SPANISH, &DUTCH];
+
+// Look a language up by its code or name, ignoring case.
+pub fn find_language(name: &str) -> Option<&'static Language> {
+    LANGUAGES
+        .iter()
+        .find(|x| x.code.eq_ignore_ascii_case(name) || x.name.eq_ignore_ascii_case(name))
+        .copied()
+}
+
+// Letter a to z as 0 to 25, with the accented letters of the built in
+// languages folded to the letter they're built on.
+pub fn fold_letter(c: char) -> Option<usize> {
+    let base = match c {
+        'a'..='z' => c,
+        'A'..='Z' => c.to_ascii_lowercase(),
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'æ' | 'À' | 'Á' | 'Â' | 'Ã' | 'Ä' | 'Å' | 'Æ' => {
+            'a'
+        }
+        'ç' | 'Ç' => 'c',
+        'è' | 'é' | 'ê' | 'ë' | 'È' | 'É' | 'Ê' | 'Ë' => 'e',
+        'ì' | 'í' | 'î' | 'ï' | 'Ì' | 'Í' | 'Î' | 'Ï' => 'i',
+        'ñ' | 'Ñ' => 'n',
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'œ' | 'Ò' | 'Ó' | 'Ô' | 'Õ' | 'Ö' | 'Ø' | 'Œ' => {
+            'o'
+        }
+        'ù' | 'ú' | 'û' | 'ü' | 'Ù' | 'Ú' | 'Û' | 'Ü' => 'u',
+        'ý' | 'ÿ' | 'Ý' | 'Ÿ' => 'y',
+        'ß' => 's',
+        _ => return None,
+    };
+
+    Some((base as u8 - b'a') as usize)
+}
+
+// Every built in language with the confidence, 0 to 1, that the text is in
+// it, most likely first. Confidences come from how likely each language's
+// n-gram model finds the text so longer texts give surer answers.
+pub fn detect_language(text: &str) -> Vec<(&'static Language, f64)> {
+    let likelihoods = LANGUAGES
+        .iter()
+        .map(|x| x.ngram_model().log_probability(text))
+        .collect::<Vec<f64>>();
+
+    // likelihoods are log10, scale by the best one so they don't underflow
+    let best = likelihoods.iter().fold(f64::NEG_INFINITY, |a, b| a.max(*b));
+    let weights = likelihoods
+        .iter()
+        .map(|x| 10_f64.powf(x - best))
+        .collect::<Vec<f64>>();
+    let total = weights.iter().sum::<f64>();
+
+    let mut guesses = LANGUAGES
+        .iter()
+        .copied()
+        .zip(weights.iter().map(|x| x / total))
+        .collect::<Vec<(&Language, f64)>>();
+    guesses.sort_by(|a, b| descending(a.1, b.1));
+
+    guesses
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::language::*;
+    use crate::ngram::NgramModel;
+    use crate::statistics::ENGLISH_IC;
+
+    #[test]
+    fn test_find_language() {
+        assert_eq!("fr", find_language("FR").unwrap().code);
+        assert_eq!("de", find_language("German").unwrap().code);
+        assert!(find_language("klingon").is_none());
+    }
+
+    #[test]
+    fn test_index_of_coincidence() {
+        assert!((ENGLISH.index_of_coincidence() - ENGLISH_IC).abs() < 0.005);
+        // french leans harder on e, a, s and so on than english
+        assert!(FRENCH.index_of_coincidence() > ENGLISH.index_of_coincidence());
+    }
+
+    #[test]
+    fn test_fold_letter() {
+        assert_eq!(Some(4), fold_letter('é'));
+        assert_eq!(Some(4), fold_letter('E'));
+        assert_eq!(Some(18), fold_letter('ß'));
+        assert_eq!(Some(13), fold_letter('Ñ'));
+        assert_eq!(None, fold_letter('1'));
+        assert_eq!(None, fold_letter('ж'));
+    }
+
+    #[test]
+    fn test_frequency_tables() {
+        for language in LANGUAGES {
+            let total = language.frequencies.iter().sum::<f64>();
+            assert!(
+                (total - 1.0).abs() < 0.01,
+                "{} sums to {total}",
+                language.name
+            );
+        }
+    }
+
+    #[test]
+    fn test_embedded_models() {
+        let corpora = [
+            (&ENGLISH, "pride_prejudice.txt"),
+            (&FRENCH, "data/corpora/french.txt"),
+            (&GERMAN, "data/corpora/german.txt"),
+            (&SPANISH, "data/corpora/spanish.txt"),
+            (&DUTCH, "data/corpora/dutch.txt"),
+        ];
+
+        for (language, path) in corpora {
+            let corpus = std::fs::read_to_string(path).expect("failed to open corpus");
+            let trained = NgramModel::train(&corpus, 4).unwrap();
+
+            if std::env::var_os("UPDATE_NGRAMS").is_some() {
+                let path = format!("data/{}_quadgrams.bin", language.name);
+                std::fs::write(path, trained.to_bytes()).unwrap();
+                continue;
+            }
+
+            assert_eq!(
+                trained.to_bytes(),
+                language.ngram_model().to_bytes(),
+                "embedded {} model is stale, rerun with UPDATE_NGRAMS=1",
+                language.name
+            );
+        }
+    }
+
+    #[test]
+    fn test_detect_language() {
+        let samples = [
+            (
+                "en",
+                "The weather was cold and the children stayed inside all afternoon.",
+            ),
+            (
+                "fr",
+                "Nous avons mangé une soupe délicieuse chez mes grands-parents hier soir.",
+            ),
+            (
+                "de",
+                "Wir haben gestern Abend bei meinen Großeltern eine leckere Suppe gegessen.",
+            ),
+            (
+                "es",
+                "Anoche comimos una sopa deliciosa en casa de mis abuelos.",
+            ),
+            (
+                "nl",
+                "We hebben gisteravond bij mijn grootouders een heerlijke soep gegeten.",
+            ),
+        ];
+
+        for (code, text) in samples {
+            let guesses = detect_language(text);
+
+            assert_eq!(code, guesses[0].0.code, "{text}");
+            assert!(guesses[0].1 > 0.5);
+            assert!((guesses.iter().map(|x| x.1).sum::<f64>() - 1.0).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_detect_without_letters() {
+        let guesses = detect_language("1234");
+
+        assert!(guesses.iter().all(|x| x.1 == 0.2));
+    }
+}
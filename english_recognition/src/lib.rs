@@ -1,15 +1,43 @@
+pub mod bytes;
+pub mod language;
+pub mod ngram;
+pub mod scorer;
+pub mod segment;
+pub mod statistics;
+
 pub mod frequency_analysis {
-    use std::collections::HashMap;
+    use crate::language::{fold_letter, ENGLISH};
+    use crate::scorer::{descending, Bhattacharyya, Scorer};
+
+    // frequencey of the english language, a to z
+    pub const ENGLISH_FREQUENCIES: [f64; 26] = [
+        0.08167, 0.01492, 0.02782, 0.04253, 0.1270, 0.02228, 0.02015, 0.06094, 0.06966, 0.00153,
+        0.00772, 0.04025, 0.02406, 0.06749, 0.07507, 0.01929, 0.00095, 0.05987, 0.06327, 0.09056,
+        0.02758, 0.00978, 0.02360, 0.00150, 0.01974, 0.00074,
+    ];
 
     pub fn score_strings(candidates: Vec<String>) -> Vec<(String, f64)> {
+        score_strings_with(candidates, &Bhattacharyya(&ENGLISH))
+    }
+
+    // Same as score_strings ranked by any scorer, e.g. ngram::english().
+    pub fn score_strings_with(candidates: Vec<String>, scorer: &dyn Scorer) -> Vec<(String, f64)> {
+        score_strings_by(candidates, |c| scorer.score(c))
+    }
+
+    // Same as score_strings with another scoring function, e.g. score_quadgrams.
+    pub fn score_strings_by(
+        candidates: Vec<String>,
+        score: impl Fn(&str) -> f64,
+    ) -> Vec<(String, f64)> {
         // Assign a score to each string in Vec
         let mut temp = candidates
             .iter()
-            .map(move |c| (c.to_string(), score_text(&c)))
+            .map(move |c| (c.to_string(), score(c)))
             .collect::<Vec<(String, f64)>>();
 
         // Order by highest score first
-        temp.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+        temp.sort_by(|a, b| descending(a.1, b.1));
 
         // return scored strings
         temp
@@ -19,56 +47,40 @@ pub mod frequency_analysis {
     // based off stackoverflow article it implements the 'Bhattacharyya Coefficient' and links to the below github
     // https://crypto.stackexchange.com/questions/30209/developing-algorithm-for-detecting-plain-text-via-frequency-analysis
     pub fn score_text(candidate: &str) -> f64 {
-        // frequencey of the english language
-        let letter_freq = HashMap::from([
-            ('a', 0.08167),
-            ('b', 0.01492),
-            ('c', 0.02782),
-            ('d', 0.04253),
-            ('e', 0.1270),
-            ('f', 0.02228),
-            ('g', 0.02015),
-            ('h', 0.06094),
-            ('i', 0.06966),
-            ('j', 0.00153),
-            ('k', 0.00772),
-            ('l', 0.04025),
-            ('m', 0.02406),
-            ('n', 0.06749),
-            ('o', 0.07507),
-            ('p', 0.01929),
-            ('q', 0.00095),
-            ('r', 0.05987),
-            ('s', 0.06327),
-            ('t', 0.09056),
-            ('u', 0.02758),
-            ('v', 0.00978),
-            ('w', 0.02360),
-            ('x', 0.00150),
-            ('y', 0.01974),
-            ('z', 0.00074),
-        ]);
-
-        // how many times each letter is in candidate
-        let population: HashMap<char, f64> = candidate
-            .chars()
-            .map(|c| (c.to_ascii_lowercase(), candidate.matches(c).count() as f64))
-            .collect();
-
-        //calculate 'Bhattacharyya Coefficient'
+        bhattacharyya(candidate, &ENGLISH_FREQUENCIES)
+    }
+
+    // 'Bhattacharyya Coefficient' of the letters in candidate against any
+    // letter frequencies a to z, see language for other languages.
+    pub fn bhattacharyya(candidate: &str, frequencies: &[f64; 26]) -> f64 {
+        // how many times each letter is in candidate, ignoring case and
+        // accents, in one pass
+        let mut population = [0_usize; 26];
+        let mut length = 0;
+        for c in candidate.chars() {
+            if let Some(letter) = fold_letter(c) {
+                population[letter] += 1;
+            }
+            length += 1;
+        }
+
+        if length == 0 {
+            return 0.0;
+        }
+
+        //calculate 'Bhattacharyya Coefficient', anything not a letter counts
+        // towards the length so symbols drag the score down
         population
             .iter()
-            .map(|(x, y)| match letter_freq.get(x) {
-                Some(n) => ((n * *y) / (candidate.len() as f64)).sqrt(),
-                None => 0.0,
-            })
+            .zip(frequencies)
+            .map(|(count, frequency)| (frequency * *count as f64 / length as f64).sqrt())
             .sum()
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::frequency_analysis::{score_strings, score_text};
+    use crate::frequency_analysis::{score_strings, score_strings_by, score_text};
     use std::io::Read;
 
     #[test]
@@ -76,7 +88,7 @@ mod tests {
         let outcome = score_strings(vec!["candidates".to_string()]);
 
         assert!(outcome.len() == 1);
-        assert!(outcome[0].0 == "candidates".to_string());
+        assert!(outcome[0].0 == "candidates");
         assert!((outcome[0].1 - 0.7257) < 0.0001);
     }
 
@@ -95,6 +107,19 @@ mod tests {
         assert!(score_text(&"In recent years we have witnessed an explosion of Internet-connected applications. Whether it is a new mobile app to find your soulmate, the latest wearable to monitor your vitals, or an industrial solution to detect corrosion, our life is becoming packed with connected systems.".to_ascii_lowercase()) - score_text(&"In recent years we have witnessed an explosion of Internet-connected applications. Whether it is a new mobile app to find your soulmate, the latest wearable to monitor your vitals, or an industrial solution to detect corrosion, our life is becoming packed with connected systems.".to_ascii_uppercase()) < 0.00001);
     }
 
+    #[test]
+    fn test_mixed_case_counts_together() {
+        assert_eq!(score_text("aAbB zZ"), score_text("aabb zz"));
+        assert_eq!(score_text("Hello"), score_text("hELLO"));
+    }
+
+    #[test]
+    fn test_score_text_counts_characters() {
+        // accented letters are one character each, not two bytes
+        assert_eq!(score_text("ééab"), score_text("eeab"));
+        assert_eq!(0.0, score_text(""));
+    }
+
     #[test]
     fn test_a_book() {
         use std::fs::File;
@@ -119,4 +144,18 @@ mod tests {
 
         assert_eq!("TRIP DISPLAY SOLID BLAME", scored[0].0);
     }
+
+    #[test]
+    fn test_score_strings_by_nan() {
+        // a NaN score sorts last instead of panicking
+        let candidates = vec!["a".to_string(), "b".to_string(), "c".to_string()];
+        let scored = score_strings_by(candidates, |c| match c {
+            "b" => f64::NAN,
+            _ => c.len() as f64,
+        });
+
+        assert_eq!(3, scored.len());
+        assert_eq!("b", scored[2].0);
+        assert!(scored[2].1.is_nan());
+    }
 }
@@ -0,0 +1,508 @@
+// N-gram language model scoring text by the log probability of each letter
+// given the few before it. Single letter frequencies can't tell short
+// candidates apart, runs like "tion" and "ther" can.
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use crate::language::{fold_letter, ENGLISH};
+
+pub const MAX_ORDER: usize = 4;
+
+// Start of every saved model, followed by the format version.
+const MAGIC: [u8; 4] = *b"CTNG";
+pub const FORMAT_VERSION: u8 = 1;
+
+const LETTERS: usize = 26;
+
+// Factor applied each time scoring falls back to a shorter context.
+const BACKOFF: f64 = 0.4;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ModelError {
+    InvalidOrder(usize),
+    EmptyCorpus,
+    Truncated,
+    // Entry at the byte offset is out of range or has no count.
+    InvalidEntry(usize),
+    NotAModel,
+    UnsupportedVersion(u8),
+    File { path: String, message: String },
+}
+
+impl fmt::Display for ModelError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ModelError::InvalidOrder(order) => {
+                write!(f, "n-gram order must be 1 to {MAX_ORDER}, not {order}")
+            }
+            ModelError::EmptyCorpus => write!(f, "corpus has no n-grams of letters"),
+            ModelError::Truncated => write!(f, "model data ends early"),
+            ModelError::InvalidEntry(offset) => write!(f, "invalid n-gram entry at byte {offset}"),
+            ModelError::NotAModel => write!(f, "not an n-gram model"),
+            ModelError::UnsupportedVersion(version) => write!(
+                f,
+                "n-gram model format version {version} is not supported, expected {FORMAT_VERSION}"
+            ),
+            ModelError::File { path, message } => write!(f, "{path}: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for ModelError {}
+
+#[derive(Debug, Clone)]
+pub struct NgramModel {
+    order: usize,
+    // observed n-grams of each length from 1 to order as (index, count) sorted
+    // by index, an index is the letters as a base 26 number
+    counts: Vec<Vec<(u32, u32)>>,
+    // log10 probability of the last letter of each n-gram given the ones
+    // before it, negative infinity where it was never seen
+    log_probs: Vec<Vec<f32>>,
+    // log10 probability for a letter that was never seen at all
+    floor: f32,
+}
+
+impl NgramModel {
+    // Count every run of up to order letters in the corpus, ignoring case and
+    // accents and skipping anything that isn't a letter.
+    pub fn train(corpus: &str, order: usize) -> Result<NgramModel, ModelError> {
+        table_size(order)?;
+
+        let counts = (1..=order)
+            .map(|length| {
+                let mut dense = vec![0_u32; LETTERS.pow(length as u32)];
+                for index in ngram_indices(corpus, length) {
+                    dense[index] += 1;
+                }
+
+                dense
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, count)| **count > 0)
+                    .map(|(index, count)| (index as u32, *count))
+                    .collect()
+            })
+            .collect();
+
+        NgramModel::from_counts(order, counts)
+    }
+
+    // Unseen letters get a floor of a hundredth of a single sighting so one
+    // odd letter doesn't sink an otherwise good candidate.
+    fn from_counts(order: usize, counts: Vec<Vec<(u32, u32)>>) -> Result<NgramModel, ModelError> {
+        let mut dense = Vec::with_capacity(order);
+        for (length, sparse) in (1..=order).zip(&counts) {
+            let mut table = vec![0_u32; table_size(length)?];
+            for (index, count) in sparse {
+                table[*index as usize] = *count;
+            }
+            dense.push(table);
+        }
+
+        let total = dense[0].iter().map(|x| *x as f64).sum::<f64>();
+        if total == 0.0 {
+            return Err(ModelError::EmptyCorpus);
+        }
+
+        // each n-gram over the count of the n-gram one letter shorter in front
+        // of it, letters alone are over the total
+        let log_probs = dense
+            .iter()
+            .enumerate()
+            .map(|(length, table)| {
+                table
+                    .iter()
+                    .enumerate()
+                    .map(|(index, count)| {
+                        let context = match length {
+                            0 => total,
+                            _ => dense[length - 1][index / LETTERS] as f64,
+                        };
+
+                        match count {
+                            0 => f32::NEG_INFINITY,
+                            count => (*count as f64 / context).log10() as f32,
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+
+        Ok(NgramModel {
+            order,
+            counts,
+            log_probs,
+            floor: (0.01 / total).log10() as f32,
+        })
+    }
+
+    pub fn order(&self) -> usize {
+        self.order
+    }
+
+    // Letters the model was trained on.
+    pub fn sample_size(&self) -> u64 {
+        self.counts[0].iter().map(|(_, count)| *count as u64).sum()
+    }
+
+    // Distinct n-grams of every length the model has seen.
+    pub fn distinct(&self) -> usize {
+        self.counts.iter().map(|x| x.len()).sum()
+    }
+
+    // Sum of the log10 probability of every letter in the text given up to
+    // order - 1 letters before it.
+    pub fn log_probability(&self, text: &str) -> f64 {
+        self.letter_log_probs(text).sum()
+    }
+
+    // Average log10 probability per letter so texts of different lengths
+    // compare, closer to zero is more likely. Text without letters gets the
+    // floor.
+    pub fn score(&self, text: &str) -> f64 {
+        let (sum, count) = self
+            .letter_log_probs(text)
+            .fold((0.0, 0), |(sum, count), x| (sum + x, count + 1));
+
+        match count {
+            0 => self.floor as f64,
+            count => sum / count as f64,
+        }
+    }
+
+    fn letter_log_probs<'a>(&'a self, text: &'a str) -> impl Iterator<Item = f64> + 'a {
+        let size = LETTERS.pow(self.order as u32);
+
+        letters(text).scan((0, 0), move |(history, seen), letter| {
+            *history = (*history * LETTERS + letter) % size;
+            *seen += 1;
+            Some(self.backoff(*history, self.order.min(*seen)))
+        })
+    }
+
+    // Probability of the newest letter in history using the longest context
+    // that was seen in training, each step back to a shorter one costs a
+    // constant factor.
+    fn backoff(&self, history: usize, longest: usize) -> f64 {
+        let mut penalty = 0.0;
+
+        for length in (1..=longest).rev() {
+            let log_prob = self.log_probs[length - 1][history % LETTERS.pow(length as u32)];
+            if log_prob.is_finite() {
+                return log_prob as f64 + penalty;
+            }
+
+            penalty += BACKOFF.log10();
+        }
+
+        self.floor as f64
+    }
+
+    // Compact form for embedding and saving. The magic bytes "CTNG", the
+    // format version and the order, then for each n-gram length the number of
+    // n-grams followed by each as the gap from the previous index and its
+    // count, all as LEB128 varints.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(FORMAT_VERSION);
+        bytes.push(self.order as u8);
+
+        for sparse in &self.counts {
+            write_varint(&mut bytes, sparse.len() as u32);
+
+            let mut previous = 0;
+            for (index, count) in sparse {
+                write_varint(&mut bytes, index - previous);
+                write_varint(&mut bytes, *count);
+                previous = *index;
+            }
+        }
+
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<NgramModel, ModelError> {
+        let rest = bytes.strip_prefix(&MAGIC).ok_or(ModelError::NotAModel)?;
+        let (&version, rest) = rest.split_first().ok_or(ModelError::Truncated)?;
+        if version != FORMAT_VERSION {
+            return Err(ModelError::UnsupportedVersion(version));
+        }
+
+        let (&order, mut rest) = rest.split_first().ok_or(ModelError::Truncated)?;
+        let order = order as usize;
+        table_size(order)?;
+
+        let mut counts = Vec::with_capacity(order);
+        for length in 1..=order {
+            let size = LETTERS.pow(length as u32);
+            let entries = read_varint(&mut rest)?;
+
+            let mut sparse = Vec::new();
+            let mut index = 0_u32;
+            for _ in 0..entries {
+                let offset = bytes.len() - rest.len();
+
+                let gap = read_varint(&mut rest)?;
+                let count = read_varint(&mut rest)?;
+
+                // indices only go up, bar the first which may be zero
+                index = match index.checked_add(gap) {
+                    Some(x) if (x as usize) < size && (gap > 0 || sparse.is_empty()) => x,
+                    _ => return Err(ModelError::InvalidEntry(offset)),
+                };
+                if count == 0 {
+                    return Err(ModelError::InvalidEntry(offset));
+                }
+
+                sparse.push((index, count));
+            }
+
+            counts.push(sparse);
+        }
+
+        if !rest.is_empty() {
+            return Err(ModelError::InvalidEntry(bytes.len() - rest.len()));
+        }
+
+        NgramModel::from_counts(order, counts)
+    }
+
+    pub fn load(path: &Path) -> Result<NgramModel, ModelError> {
+        let bytes = fs::read(path).map_err(|e| file_error(path, e))?;
+
+        NgramModel::from_bytes(&bytes)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), ModelError> {
+        fs::write(path, self.to_bytes()).map_err(|e| file_error(path, e))
+    }
+}
+
+fn file_error(path: &Path, error: impl fmt::Display) -> ModelError {
+    ModelError::File {
+        path: path.display().to_string(),
+        message: error.to_string(),
+    }
+}
+
+fn table_size(order: usize) -> Result<usize, ModelError> {
+    match order {
+        1..=MAX_ORDER => Ok(LETTERS.pow(order as u32)),
+        _ => Err(ModelError::InvalidOrder(order)),
+    }
+}
+
+// Letters of the text as 0 to 25 with accents folded, everything else is
+// skipped.
+fn letters(text: &str) -> impl Iterator<Item = usize> + '_ {
+    text.chars().filter_map(fold_letter)
+}
+
+// Index of every n-gram in the text, rolling the base 26 number along one
+// letter at a time.
+fn ngram_indices(text: &str, order: usize) -> impl Iterator<Item = usize> + '_ {
+    let size = LETTERS.pow(order as u32);
+
+    letters(text)
+        .scan((0, 0), move |(index, seen), letter| {
+            *index = (*index * LETTERS + letter) % size;
+            *seen += 1;
+            Some((*seen >= order).then_some(*index))
+        })
+        .flatten()
+}
+
+fn write_varint(bytes: &mut Vec<u8>, mut value: u32) {
+    while value >= 0x80 {
+        bytes.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+fn read_varint(bytes: &mut &[u8]) -> Result<u32, ModelError> {
+    let mut value = 0_u32;
+
+    for shift in (0..32).step_by(7) {
+        let (&byte, rest) = bytes.split_first().ok_or(ModelError::Truncated)?;
+        *bytes = rest;
+
+        value |= ((byte & 0x7f) as u32) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+
+    Err(ModelError::Truncated)
+}
+
+// The embedded english quadgram model.
+pub fn english() -> &'static NgramModel {
+    ENGLISH.ngram_model()
+}
+
+// Englishness by quadgram log probability per letter, higher is more english.
+pub fn score_quadgrams(candidate: &str) -> f64 {
+    english().score(candidate)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::frequency_analysis::score_strings_with;
+    use crate::ngram::{english, score_quadgrams, ModelError, NgramModel};
+
+    fn round(x: f64) -> f64 {
+        (x * 1e6).round() / 1e6
+    }
+
+    // Saved model with the header in front of the order and counts.
+    fn model(body: &[u8]) -> Vec<u8> {
+        [b"CTNG\x01".as_slice(), body].concat()
+    }
+
+    #[test]
+    fn test_train_counts_letters() {
+        let model = NgramModel::train("Ab, c-AB!", 2).unwrap();
+
+        assert_eq!(vec![(0, 2), (1, 2), (2, 1)], model.counts[0]);
+        // ab bc ca ab
+        assert_eq!(vec![(1, 2), (28, 1), (52, 1)], model.counts[1]);
+
+        // a, b after a, c after b, a after c, b after a
+        assert_eq!(
+            round(0.4_f64.log10() + 0.5_f64.log10()),
+            round(model.log_probability("abcab"))
+        );
+    }
+
+    #[test]
+    fn test_backoff_and_floor() {
+        let model = NgramModel::train("abcab", 2).unwrap();
+
+        // b never followed by a, falls back to a alone
+        assert_eq!(
+            round(0.4_f64.log10() * 2.0 + 0.4_f64.log10()),
+            round(model.log_probability("ba"))
+        );
+        assert_eq!(model.floor as f64, model.score("zz"));
+        assert_eq!(model.floor as f64, model.score("12"));
+        assert!(model.score("ab") > model.score("ba"));
+    }
+
+    #[test]
+    fn test_invalid_models() {
+        assert_eq!(
+            ModelError::InvalidOrder(5),
+            NgramModel::train("abcdef", 5).unwrap_err()
+        );
+        assert_eq!(
+            ModelError::InvalidOrder(0),
+            NgramModel::train("abcdef", 0).unwrap_err()
+        );
+        assert_eq!(
+            ModelError::EmptyCorpus,
+            NgramModel::train("12 34", 3).unwrap_err()
+        );
+
+        assert_eq!(
+            ModelError::NotAModel,
+            NgramModel::from_bytes(&[]).unwrap_err()
+        );
+        assert_eq!(
+            ModelError::NotAModel,
+            NgramModel::from_bytes(b"CTNX\x01\x01").unwrap_err()
+        );
+        assert_eq!(
+            ModelError::UnsupportedVersion(2),
+            NgramModel::from_bytes(b"CTNG\x02\x01").unwrap_err()
+        );
+        assert_eq!(
+            ModelError::Truncated,
+            NgramModel::from_bytes(b"CTNG").unwrap_err()
+        );
+        assert_eq!(
+            ModelError::Truncated,
+            NgramModel::from_bytes(&model(&[1, 1, 0])).unwrap_err()
+        );
+        assert_eq!(
+            ModelError::Truncated,
+            NgramModel::from_bytes(&model(&[1, 0x81])).unwrap_err()
+        );
+        // index past the end of the single letter table
+        assert_eq!(
+            ModelError::InvalidEntry(7),
+            NgramModel::from_bytes(&model(&[1, 1, 26, 1])).unwrap_err()
+        );
+        // zero count
+        assert_eq!(
+            ModelError::InvalidEntry(7),
+            NgramModel::from_bytes(&model(&[1, 1, 3, 0])).unwrap_err()
+        );
+        // trailing bytes
+        assert_eq!(
+            ModelError::InvalidEntry(9),
+            NgramModel::from_bytes(&model(&[1, 1, 3, 1, 0])).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn test_bytes_round_trip() {
+        for order in 1..=4 {
+            let model =
+                NgramModel::train("the quick brown fox jumps over the lazy dog", order).unwrap();
+            let loaded = NgramModel::from_bytes(&model.to_bytes()).unwrap();
+
+            assert_eq!(model.counts, loaded.counts);
+            assert_eq!(model.log_probs, loaded.log_probs);
+        }
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let path = std::env::temp_dir().join(format!("ngram-{}.bin", std::process::id()));
+        let model = NgramModel::train("fn main() { println!(\"hello\"); }", 3).unwrap();
+
+        model.save(&path).unwrap();
+        let loaded = NgramModel::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(model.counts, loaded.counts);
+        assert_eq!(3, loaded.order());
+        assert_eq!(18, loaded.sample_size());
+        assert!(matches!(
+            NgramModel::load(&path),
+            Err(ModelError::File { .. })
+        ));
+    }
+
+    #[test]
+    fn test_english_beats_gibberish() {
+        assert!(
+            score_quadgrams("It is a truth universally acknowledged")
+                > score_quadgrams("Xq zjv k wpfbm ghyxcvqz uowjkdlqnf")
+        );
+    }
+
+    #[test]
+    fn test_caesar_cipher_selection() {
+        use caesar::caesar::brute_force;
+
+        let out = brute_force("IGXE SXHEAPN HDAXS QAPBT");
+
+        let scored = score_strings_with(out, english());
+
+        assert_eq!("TRIP DISPLAY SOLID BLAME", scored[0].0);
+    }
+
+    #[test]
+    fn test_short_caesar_selection() {
+        use caesar::caesar::brute_force;
+
+        // too short for the single letter score to pick out
+        let scored = score_strings_with(brute_force("Wkh fdw"), english());
+
+        assert_eq!("The cat", scored[0].0);
+    }
+}
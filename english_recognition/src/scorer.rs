@@ -0,0 +1,308 @@
+// Ways of ranking how likely a candidate is to be the plaintext. Every scorer
+// gives higher scores to better candidates, though the scales differ, so mix
+// them with a Composite rather than comparing them directly.
+use regex::Regex;
+use std::cmp::Ordering;
+use std::collections::HashSet;
+
+use crate::language::{Language, ENGLISH};
+use crate::ngram::NgramModel;
+use crate::statistics::{chi_squared, letter_counts};
+
+// Flag formats from CTFs, e.g. flag{...}, CTF{...} or picoCTF{...}.
+const FLAG_PATTERN: &str = r"(?i)[a-z0-9_]*(flag|ctf)\{[^{}\s]+\}";
+
+pub trait Scorer {
+    fn score(&self, candidate: &str) -> f64;
+
+    // Raw bytes such as decoded output. Text scorers see them through a lossy
+    // UTF-8 conversion, byte scorers like bytes::ByteModel see every byte.
+    fn score_bytes(&self, candidate: &[u8]) -> f64 {
+        self.score(&String::from_utf8_lossy(candidate))
+    }
+}
+
+// Orders scores best first for sort_by. A NaN score ranks below everything,
+// negative infinity included, where total_cmp alone would put a positive NaN
+// first.
+pub fn descending(a: f64, b: f64) -> Ordering {
+    a.is_nan().cmp(&b.is_nan()).then(b.total_cmp(&a))
+}
+
+// Lets shared scorers like ngram::english() be boxed or mixed into a Composite.
+impl<T: Scorer + ?Sized> Scorer for &T {
+    fn score(&self, candidate: &str) -> f64 {
+        (**self).score(candidate)
+    }
+
+    fn score_bytes(&self, candidate: &[u8]) -> f64 {
+        (**self).score_bytes(candidate)
+    }
+}
+
+// The original letter frequency score, 1 for the language down to 0.
+pub struct Bhattacharyya(pub &'static Language);
+
+impl Scorer for Bhattacharyya {
+    fn score(&self, candidate: &str) -> f64 {
+        self.0.score(candidate)
+    }
+}
+
+// Chi-squared distance of the letter counts from the language, negated so
+// closer is higher. Text without letters scores negative infinity.
+pub struct ChiSquared(pub &'static Language);
+
+impl Scorer for ChiSquared {
+    fn score(&self, candidate: &str) -> f64 {
+        let counts = letter_counts(candidate);
+
+        match counts.iter().sum::<usize>() {
+            0 => f64::NEG_INFINITY,
+            _ => -chi_squared(&counts, &self.0.frequencies),
+        }
+    }
+}
+
+// Average log probability per letter, see Language::ngram_model for the built
+// in models.
+impl Scorer for NgramModel {
+    fn score(&self, candidate: &str) -> f64 {
+        NgramModel::score(self, candidate)
+    }
+}
+
+// Fraction of the words in the candidate found in a dictionary, 0 to 1.
+pub struct Dictionary {
+    words: HashSet<String>,
+}
+
+impl Dictionary {
+    pub fn new<'a>(words: impl IntoIterator<Item = &'a str>) -> Dictionary {
+        Dictionary {
+            words: words.into_iter().map(|x| x.to_lowercase()).collect(),
+        }
+    }
+
+    // The language's most common words, enough to spot prose without a full
+    // list.
+    pub fn language(language: &Language) -> Dictionary {
+        Dictionary::new(language.common_words.iter().copied())
+    }
+
+    pub fn english() -> Dictionary {
+        Dictionary::language(&ENGLISH)
+    }
+}
+
+impl Scorer for Dictionary {
+    fn score(&self, candidate: &str) -> f64 {
+        let words = candidate
+            .split(|x: char| !x.is_alphabetic() && x != '\'')
+            .filter(|x| !x.is_empty())
+            .map(|x| x.to_lowercase())
+            .collect::<Vec<String>>();
+
+        if words.is_empty() {
+            return 0.0;
+        }
+
+        let hits = words.iter().filter(|x| self.words.contains(*x)).count();
+
+        hits as f64 / words.len() as f64
+    }
+}
+
+// Fraction of printable ASCII or whitespace characters, 0 to 1.
+pub struct Printable;
+
+impl Scorer for Printable {
+    fn score(&self, candidate: &str) -> f64 {
+        let (printable, total) = candidate.chars().fold((0, 0), |(printable, total), x| {
+            match x.is_ascii_graphic() || x.is_ascii_whitespace() {
+                true => (printable + 1, total + 1),
+                false => (printable, total + 1),
+            }
+        });
+
+        match total {
+            0 => 0.0,
+            total => printable as f64 / total as f64,
+        }
+    }
+}
+
+// 1 if the candidate contains a match for a regex, otherwise 0.
+pub struct Pattern {
+    regex: Regex,
+}
+
+impl Pattern {
+    pub fn new(pattern: &str) -> Result<Pattern, regex::Error> {
+        Ok(Pattern {
+            regex: Regex::new(pattern)?,
+        })
+    }
+
+    pub fn flag() -> Pattern {
+        Pattern::new(FLAG_PATTERN).expect("flag pattern is valid")
+    }
+}
+
+impl Scorer for Pattern {
+    fn score(&self, candidate: &str) -> f64 {
+        match self.regex.is_match(candidate) {
+            true => 1.0,
+            false => 0.0,
+        }
+    }
+}
+
+// Weighted sum of other scorers.
+#[derive(Default)]
+pub struct Composite {
+    scorers: Vec<(f64, Box<dyn Scorer + Send + Sync>)>,
+}
+
+impl Composite {
+    pub fn new() -> Composite {
+        Composite::default()
+    }
+
+    pub fn with(mut self, weight: f64, scorer: impl Scorer + Send + Sync + 'static) -> Composite {
+        self.scorers.push((weight, Box::new(scorer)));
+        self
+    }
+
+    // Mix of scorers that each give 0 to 1 for picking english or a flag out
+    // of anything.
+    pub fn english() -> Composite {
+        Composite::language(&ENGLISH, Pattern::flag())
+    }
+
+    // Same mix for any language, looking for any pattern.
+    pub fn language(language: &'static Language, pattern: Pattern) -> Composite {
+        Composite::new()
+            .with(1.0, Bhattacharyya(language))
+            .with(1.0, Dictionary::language(language))
+            .with(1.0, Printable)
+            .with(1.0, pattern)
+    }
+}
+
+impl Scorer for Composite {
+    fn score(&self, candidate: &str) -> f64 {
+        self.scorers
+            .iter()
+            .map(|(weight, scorer)| weight * scorer.score(candidate))
+            .sum()
+    }
+
+    fn score_bytes(&self, candidate: &[u8]) -> f64 {
+        self.scorers
+            .iter()
+            .map(|(weight, scorer)| weight * scorer.score_bytes(candidate))
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::frequency_analysis::score_strings_with;
+    use crate::language::{ENGLISH, FRENCH};
+    use crate::ngram::english;
+    use crate::scorer::{
+        descending, Bhattacharyya, ChiSquared, Composite, Dictionary, Pattern, Printable, Scorer,
+    };
+    use caesar::caesar::brute_force;
+
+    const ENGLISH_TEXT: &str = "It is a truth universally acknowledged, that a single man in possession of a good fortune, must be in want of a wife.";
+    const GIBBERISH: &str = "Xq zjv k wpfbm ghyxcvqz uowjkdlqnf, zxqv w jkqpz";
+
+    #[test]
+    fn test_descending_puts_nan_last() {
+        let mut scores = [1.0, f64::NAN, -f64::NAN, f64::NEG_INFINITY, 2.0];
+        scores.sort_by(|a, b| descending(*a, *b));
+
+        assert_eq!(vec![2.0, 1.0, f64::NEG_INFINITY], scores[..3].to_vec());
+        assert!(scores[3..].iter().all(|x| x.is_nan()));
+    }
+
+    #[test]
+    fn test_scorers_prefer_english() {
+        let scorers: [&dyn Scorer; 5] = [
+            &Bhattacharyya(&ENGLISH),
+            &ChiSquared(&ENGLISH),
+            english(),
+            &Dictionary::english(),
+            &Composite::english(),
+        ];
+
+        for scorer in scorers {
+            assert!(scorer.score(ENGLISH_TEXT) > scorer.score(GIBBERISH));
+        }
+    }
+
+    #[test]
+    fn test_chi_squared() {
+        assert_eq!(f64::NEG_INFINITY, ChiSquared(&ENGLISH).score("1234 !?"));
+        assert!(ChiSquared(&ENGLISH).score("zzzz") < ChiSquared(&ENGLISH).score("etao"));
+    }
+
+    #[test]
+    fn test_dictionary() {
+        let dictionary = Dictionary::new(["Cat", "sat"]);
+
+        assert_eq!(0.5, dictionary.score("CAT sat, on-mats"));
+        assert_eq!(0.0, dictionary.score("123"));
+        assert_eq!(1.0, Dictionary::english().score("It is what it is"));
+        assert_eq!(1.0, Dictionary::language(&FRENCH).score("Il a été"));
+    }
+
+    #[test]
+    fn test_printable() {
+        assert_eq!(1.0, Printable.score("hello world\n"));
+        assert_eq!(0.5, Printable.score("ab\u{fffd}\u{1}"));
+        assert_eq!(0.0, Printable.score(""));
+    }
+
+    #[test]
+    fn test_pattern() {
+        let flag = Pattern::flag();
+
+        assert_eq!(1.0, flag.score("the answer is picoCTF{r0t_13_f0r_th3_w1n}"));
+        assert_eq!(0.0, flag.score("no flags {here}"));
+        assert_eq!(1.0, Pattern::new("^[0-9]+$").unwrap().score("1234"));
+        assert!(Pattern::new("(").is_err());
+    }
+
+    #[test]
+    fn test_composite_weights() {
+        let composite = Composite::new()
+            .with(2.0, Printable)
+            .with(0.5, Dictionary::english());
+
+        assert_eq!(2.5, composite.score("the"));
+        assert_eq!(0.0, Composite::new().score("the"));
+    }
+
+    #[test]
+    fn test_caesar_in_other_language() {
+        // "Nous partirons demain matin" shifted by 3
+        let out = brute_force("Qrxv sduwlurqv ghpdlq pdwlq");
+
+        let french = score_strings_with(out, &Composite::language(&FRENCH, Pattern::flag()));
+
+        assert_eq!("Nous partirons demain matin", french[0].0);
+    }
+
+    #[test]
+    fn test_caesar_flag_selection() {
+        // letter frequency alone can't pick this, the flag format can
+        let out = brute_force("synt{dhvpx_tynapr}");
+
+        let scored = score_strings_with(out, &Composite::english());
+
+        assert_eq!("flag{quick_glance}", scored[0].0);
+    }
+}
@@ -0,0 +1,238 @@
+// Word segmentation for text with its spaces stripped. The best split of a
+// run of letters is found Viterbi style as the likeliest sequence of words,
+// each word's probability following Zipf's law from its rank in a list of
+// words ordered most common first.
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use crate::scorer::Scorer;
+
+// The embedded list, one word per line, most common first.
+const ENGLISH_WORDS: &str = include_str!("../data/english_words.txt");
+
+// Extra cost of a letter no word covers over the rarest word in the list, so
+// splitting into words always wins when it can.
+const UNKNOWN_PENALTY: f64 = 2.0;
+
+pub struct WordList {
+    costs: HashMap<String, f64>,
+    longest: usize,
+    unknown: f64,
+}
+
+// Best split of a text, with the words of each run of letters spaced apart
+// and everything else left as it was.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Segmentation {
+    pub spaced: String,
+    // Fraction of the letters covered by words in the list, 0 to 1.
+    pub coverage: f64,
+}
+
+impl WordList {
+    // Words most common first, repeats keep their first rank.
+    pub fn new<'a>(words: impl IntoIterator<Item = &'a str>) -> WordList {
+        let words = words
+            .into_iter()
+            .map(|x| x.trim().to_lowercase())
+            .filter(|x| !x.is_empty())
+            .collect::<Vec<String>>();
+
+        // cost of the word ranked n is -ln(1 / (n ln N)), roughly Zipf
+        let scale = (words.len().max(2) as f64).ln();
+        let mut costs = HashMap::new();
+        for (rank, word) in words.into_iter().enumerate() {
+            costs
+                .entry(word)
+                .or_insert(((rank + 1) as f64 * scale).ln());
+        }
+
+        let longest = costs.keys().map(|x| x.chars().count()).max().unwrap_or(0);
+        let unknown = costs.values().fold(0.0_f64, |a, b| a.max(*b)) + UNKNOWN_PENALTY;
+
+        WordList {
+            costs,
+            longest,
+            unknown,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.costs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.costs.is_empty()
+    }
+
+    pub fn contains(&self, word: &str) -> bool {
+        self.costs.contains_key(&word.to_lowercase())
+    }
+
+    pub fn segment(&self, text: &str) -> Segmentation {
+        let chars = text.chars().collect::<Vec<char>>();
+
+        let mut spaced = String::with_capacity(text.len());
+        let mut letters = 0;
+        let mut covered = 0;
+
+        for run in chars.chunk_by(|a, b| a.is_alphabetic() == b.is_alphabetic()) {
+            if !run[0].is_alphabetic() {
+                spaced.extend(run);
+                continue;
+            }
+
+            for (i, (word, known)) in self.split(run).into_iter().enumerate() {
+                if i > 0 {
+                    spaced.push(' ');
+                }
+                spaced.extend(word);
+                if known {
+                    covered += word.len();
+                }
+            }
+            letters += run.len();
+        }
+
+        let coverage = match letters {
+            0 => 0.0,
+            letters => covered as f64 / letters as f64,
+        };
+
+        Segmentation { spaced, coverage }
+    }
+
+    // Cheapest split of a run of letters into words, each with whether it is
+    // in the list. Neighbouring letters no word covers come back as one piece.
+    fn split<'a>(&self, run: &'a [char]) -> Vec<(&'a [char], bool)> {
+        let lower = run
+            .iter()
+            .map(|x| x.to_ascii_lowercase())
+            .collect::<Vec<char>>();
+
+        // best[i] is the cheapest cost of the first i letters and where the
+        // last piece of it starts
+        let mut best = vec![(0.0, 0, false); run.len() + 1];
+        for end in 1..=run.len() {
+            best[end] = (best[end - 1].0 + self.unknown, end - 1, false);
+
+            for start in end.saturating_sub(self.longest)..end {
+                let word = lower[start..end].iter().collect::<String>();
+                if let Some(cost) = self.costs.get(&word) {
+                    if best[start].0 + cost < best[end].0 {
+                        best[end] = (best[start].0 + cost, start, true);
+                    }
+                }
+            }
+        }
+
+        let mut pieces = Vec::new();
+        let mut end = run.len();
+        while end > 0 {
+            let (_, start, known) = best[end];
+            match pieces.last_mut() {
+                Some((piece_start, false)) if !known => *piece_start = start,
+                _ => pieces.push((start, known)),
+            }
+            end = start;
+        }
+        pieces.reverse();
+
+        let mut words = Vec::new();
+        for (i, (start, known)) in pieces.iter().enumerate() {
+            let end = pieces.get(i + 1).map_or(run.len(), |x| x.0);
+            words.push((&run[*start..end], *known));
+        }
+
+        words
+    }
+}
+
+// Fraction of the letters covered by words, which works with or without
+// spaces.
+impl Scorer for WordList {
+    fn score(&self, candidate: &str) -> f64 {
+        self.segment(candidate).coverage
+    }
+}
+
+pub fn english() -> &'static WordList {
+    static WORDS: OnceLock<WordList> = OnceLock::new();
+    WORDS.get_or_init(|| WordList::new(ENGLISH_WORDS.lines()))
+}
+
+// Text with the spaces put back, e.g. "THEREISNOSPOON" to "THERE IS NO SPOON".
+pub fn spaced(text: &str) -> String {
+    english().segment(text).spaced
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::segment::*;
+    use caesar::caesar::brute_force;
+
+    #[test]
+    fn test_segment() {
+        let segmentation = english().segment("THEREISNOSPOON");
+
+        assert_eq!("THERE IS NO SPOON", segmentation.spaced);
+        assert_eq!(1.0, segmentation.coverage);
+
+        assert_eq!("attack at dawn", spaced("attackatdawn"));
+        assert_eq!("flag{quick glance}", spaced("flag{quickglance}"));
+        assert_eq!(
+            "the quick brown fox, jumps",
+            spaced("thequickbrownfox, jumps")
+        );
+    }
+
+    #[test]
+    fn test_unknown_letters() {
+        let words = WordList::new(["cat", "sat"]);
+        let segmentation = words.segment("xcatqqsat!");
+
+        assert_eq!("x cat qq sat!", segmentation.spaced);
+        assert_eq!(6.0 / 9.0, segmentation.coverage);
+
+        assert_eq!(0.0, words.segment("123 !?").coverage);
+        assert_eq!("123 !?", words.segment("123 !?").spaced);
+        assert_eq!("", words.segment("").spaced);
+    }
+
+    #[test]
+    fn test_rank_breaks_ties() {
+        // "the rein" and "there in" both cover everything, the common words win
+        let words = WordList::new(["the", "in", "there", "rein"]);
+
+        assert_eq!("the rein", words.segment("therein").spaced);
+
+        let words = WordList::new(["there", "in", "the", "rein"]);
+
+        assert_eq!("there in", words.segment("therein").spaced);
+    }
+
+    #[test]
+    fn test_word_list() {
+        let words = english();
+
+        assert!(words.len() > 1000);
+        assert!(words.contains("The"));
+        assert!(!words.contains("qzx"));
+        assert!(WordList::new([""]).is_empty());
+    }
+
+    #[test]
+    fn test_caesar_without_spaces() {
+        // "THEREISNOSPOON" shifted by 7
+        let mut out = brute_force("AOLYLPZUVZWVVU")
+            .into_iter()
+            .map(|x| (english().score(&x), x))
+            .collect::<Vec<(f64, String)>>();
+        out.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+        assert_eq!("THEREISNOSPOON", out[0].1);
+        // short words turn up in gibberish too, but only english is covered
+        assert_eq!((1.0, "THEREISNOSPOON".to_string()), out[0]);
+        assert!(out[1].0 < 0.75);
+    }
+}
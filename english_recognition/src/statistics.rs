@@ -0,0 +1,220 @@
+// Classical cryptanalysis statistics. Letters are a to z with case and accents
+// folded, everything else is left out of letter based measures.
+use crate::language::fold_letter;
+
+// Index of coincidence of english text and of uniformly random letters.
+pub const ENGLISH_IC: f64 = 0.0667;
+pub const RANDOM_IC: f64 = 1.0 / 26.0;
+
+// How many times each letter a to z appears.
+pub fn letter_counts(text: &str) -> [usize; 26] {
+    let mut counts = [0; 26];
+    for letter in letters(text) {
+        counts[letter] += 1;
+    }
+
+    counts
+}
+
+// Share of the letters each letter a to z makes up, all zero without letters.
+pub fn letter_frequencies(text: &str) -> [f64; 26] {
+    let counts = letter_counts(text);
+    let total = counts.iter().sum::<usize>();
+
+    counts.map(|count| match total {
+        0 => 0.0,
+        total => count as f64 / total as f64,
+    })
+}
+
+// Chi-squared distance of observed counts from a reference distribution of
+// the same length, 0 is a perfect fit. A count where the reference expects
+// nothing is infinitely far.
+pub fn chi_squared(counts: &[usize], reference: &[f64]) -> f64 {
+    let total = counts.iter().sum::<usize>() as f64;
+
+    counts
+        .iter()
+        .zip(reference)
+        .map(|(observed, frequency)| {
+            let expected = frequency * total;
+            match (*observed as f64, expected) {
+                (observed, expected) if expected > 0.0 => (observed - expected).powi(2) / expected,
+                (observed, _) if observed > 0.0 => f64::INFINITY,
+                _ => 0.0,
+            }
+        })
+        .sum()
+}
+
+// Chance two letters picked from the text are the same, around ENGLISH_IC for
+// english or anything it was transposed or monoalphabetically substituted
+// into, and around RANDOM_IC for polyalphabetic ciphers.
+pub fn index_of_coincidence(text: &str) -> f64 {
+    coincidence(&letter_counts(text))
+}
+
+// Chance two overlapping letter pairs picked from the text are the same.
+pub fn digraphic_index_of_coincidence(text: &str) -> f64 {
+    let mut counts = vec![0; 26 * 26];
+    let letters = letters(text).collect::<Vec<usize>>();
+    for pair in letters.windows(2) {
+        counts[pair[0] * 26 + pair[1]] += 1;
+    }
+
+    coincidence(&counts)
+}
+
+// Chance two bytes picked from the data are the same, 1 / 256 for random
+// bytes and far higher for text.
+pub fn byte_index_of_coincidence(bytes: &[u8]) -> f64 {
+    let mut counts = [0; 256];
+    for byte in bytes {
+        counts[*byte as usize] += 1;
+    }
+
+    coincidence(&counts)
+}
+
+fn coincidence(counts: &[usize]) -> f64 {
+    let total = counts.iter().sum::<usize>();
+    if total < 2 {
+        return 0.0;
+    }
+
+    let pairs = counts
+        .iter()
+        .map(|n| n * n.saturating_sub(1))
+        .sum::<usize>();
+
+    pairs as f64 / (total * (total - 1)) as f64
+}
+
+// Shannon entropy in bits per byte, 0 for a single repeated byte up to 8 for
+// random data.
+pub fn entropy_per_byte(bytes: &[u8]) -> f64 {
+    let mut counts = [0; 256];
+    for byte in bytes {
+        counts[*byte as usize] += 1;
+    }
+
+    entropy(counts.iter().copied())
+}
+
+// Shannon entropy in bits per character.
+pub fn entropy_per_char(text: &str) -> f64 {
+    let mut chars = text.chars().collect::<Vec<char>>();
+    chars.sort_unstable();
+
+    let counts = chars.chunk_by(|a, b| a == b).map(|x| x.len());
+
+    entropy(counts)
+}
+
+fn entropy(counts: impl Iterator<Item = usize> + Clone) -> f64 {
+    let total = counts.clone().sum::<usize>() as f64;
+
+    // adding 0 turns the -0 of no symbols or a single repeated one into 0
+    counts
+        .filter(|count| *count > 0)
+        .map(|count| {
+            let p = count as f64 / total;
+            -p * p.log2()
+        })
+        .sum::<f64>()
+        + 0.0
+}
+
+fn letters(text: &str) -> impl Iterator<Item = usize> + '_ {
+    text.chars().filter_map(fold_letter)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::frequency_analysis::ENGLISH_FREQUENCIES;
+    use crate::statistics::*;
+
+    fn round(x: f64) -> f64 {
+        (x * 1e4).round() / 1e4
+    }
+
+    #[test]
+    fn test_letter_histogram() {
+        let counts = letter_counts("Aa b-Z!");
+
+        assert_eq!(2, counts[0]);
+        assert_eq!(1, counts[1]);
+        assert_eq!(1, counts[25]);
+        assert_eq!(4, counts.iter().sum::<usize>());
+
+        assert_eq!(0.5, letter_frequencies("Aa b-Z!")[0]);
+        assert_eq!([0.0; 26], letter_frequencies("123"));
+    }
+
+    #[test]
+    fn test_chi_squared() {
+        assert_eq!(0.0, chi_squared(&[2, 2], &[0.5, 0.5]));
+        assert_eq!(1.0, chi_squared(&[3, 1], &[0.5, 0.5]));
+        assert_eq!(0.0, chi_squared(&[4, 0], &[1.0, 0.0]));
+        assert_eq!(f64::INFINITY, chi_squared(&[3, 1], &[1.0, 0.0]));
+
+        let english = letter_counts("It is a truth universally acknowledged, that a single man in possession of a good fortune, must be in want of a wife.");
+        let rotated = letter_counts("Vg vf n gehgu havirefnyyl npxabjyrqtrq, gung n fvatyr zna va cbffrffvba bs n tbbq sbeghar, zhfg or va jnag bs n jvsr.");
+        assert!(
+            chi_squared(&english, &ENGLISH_FREQUENCIES)
+                < chi_squared(&rotated, &ENGLISH_FREQUENCIES)
+        );
+    }
+
+    #[test]
+    fn test_index_of_coincidence() {
+        assert_eq!(4.0 / 12.0, index_of_coincidence("aAbB"));
+        assert_eq!(0.0, index_of_coincidence("abcd"));
+        assert_eq!(0.0, index_of_coincidence("a"));
+
+        let book = std::fs::read_to_string("pride_prejudice.txt")
+            .expect("failed to open ./pride_prejudice.txt");
+        assert!((index_of_coincidence(&book) - ENGLISH_IC).abs() < 0.005);
+    }
+
+    #[test]
+    fn test_digraphic_index_of_coincidence() {
+        // aa aa aa
+        assert_eq!(1.0, digraphic_index_of_coincidence("aaaa"));
+        // ab ba ab
+        assert_eq!(2.0 / 6.0, digraphic_index_of_coincidence("a b-a b"));
+        assert_eq!(0.0, digraphic_index_of_coincidence("ab"));
+    }
+
+    #[test]
+    fn test_byte_index_of_coincidence() {
+        assert_eq!(2.0 / 12.0, byte_index_of_coincidence(b"aAbb"));
+        assert_eq!(
+            0.0,
+            byte_index_of_coincidence(&(0..=255).collect::<Vec<u8>>())
+        );
+        assert_eq!(0.0, byte_index_of_coincidence(b""));
+    }
+
+    #[test]
+    fn test_entropy() {
+        assert_eq!(0.0, entropy_per_byte(&[7; 10]));
+        assert_eq!(2.0, entropy_per_byte(b"abcd"));
+        assert_eq!(8.0, entropy_per_byte(&(0..=255).collect::<Vec<u8>>()));
+        assert_eq!(0.0, entropy_per_byte(b""));
+
+        // positive zero, so it never prints as -0.0000
+        for entropy in [
+            entropy_per_byte(&[7; 10]),
+            entropy_per_byte(b""),
+            entropy_per_char("aaa"),
+            entropy_per_char(""),
+        ] {
+            assert!(entropy.is_sign_positive());
+        }
+
+        // two bytes each for the accented letters
+        assert_eq!(1.5, entropy_per_char("ééab"));
+        assert_eq!(1.9183, round(entropy_per_byte("ééab".as_bytes())));
+    }
+}
@@ -1,35 +1,100 @@
 pub mod hex {
-    pub fn decode(input: &str) -> Vec<u8> {
-        if (input.len() % 2 != 0) & valid_hex_character(&input) {
-            panic!("Invalid Hex string, must be multiple of 2.");
+    use std::fmt;
+    use std::io::{self, Read, Write};
+
+    // Bytes read from the inner reader at a time when stream decoding.
+    const BUFFER_SIZE: usize = 8 * 1024;
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub enum DecodeError {
+        // Odd number of hex digits, offset is the unpaired trailing digit.
+        InvalidLength { offset: usize },
+        // Character that isn't a hex digit.
+        InvalidSymbol { offset: usize, symbol: char },
+    }
+
+    impl DecodeError {
+        // Byte offset into the input where decoding failed.
+        pub fn offset(&self) -> usize {
+            match self {
+                DecodeError::InvalidLength { offset }
+                | DecodeError::InvalidSymbol { offset, .. } => *offset,
+            }
         }
+    }
 
-        input
-            .chars()
-            .collect::<Vec<char>>()
-            .chunks(2)
-            .map(hex_to_byte)
-            .collect::<Vec<u8>>()
+    impl fmt::Display for DecodeError {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            match self {
+                DecodeError::InvalidLength { offset } => {
+                    write!(f, "invalid hex length, unpaired digit at offset {offset}")
+                }
+                DecodeError::InvalidSymbol { offset, symbol } => {
+                    write!(f, "invalid hex symbol {symbol:?} at offset {offset}")
+                }
+            }
+        }
     }
 
-    fn valid_hex_character(input: &str) -> bool {
-        input
-            .chars()
-            .map(|x| match x as u8 {
-                48..=57 => true,
-                65..=90 => true,
-                97..=122 => true,
-                _ => false,
-            })
-            .fold(true, |x, y| x & y)
+    impl std::error::Error for DecodeError {}
+
+    pub fn decode(input: &str) -> Result<Vec<u8>, DecodeError> {
+        let mut decoder = DigitDecoder::new();
+        let mut output = Vec::with_capacity(input.len() / 2);
+
+        for (offset, symbol) in input.char_indices() {
+            decoder.push(offset, symbol, &mut output)?;
+        }
+        decoder.finish()?;
+
+        Ok(output)
     }
 
-    fn hex_to_byte(input: &[char]) -> u8 {
-        match input.len() {
-            2 => {
-                u8::from_str_radix(&input[0].to_string(), 16).unwrap() << 4
-                    | u8::from_str_radix(&input[1].to_string(), 16).unwrap()
+    // Decoding state fed one digit at a time, so whole strings and streams go
+    // through the same validation and report the same offsets.
+    struct DigitDecoder {
+        // high nibble waiting on its pair, with the offset of its digit
+        high: Option<(usize, u8)>,
+    }
+
+    impl DigitDecoder {
+        fn new() -> DigitDecoder {
+            DigitDecoder { high: None }
+        }
+
+        fn push(
+            &mut self,
+            offset: usize,
+            symbol: char,
+            output: &mut Vec<u8>,
+        ) -> Result<(), DecodeError> {
+            if !symbol.is_ascii_hexdigit() {
+                return Err(DecodeError::InvalidSymbol { offset, symbol });
+            }
+            let nibble = hex_to_nibble(symbol as u8);
+
+            match self.high.take() {
+                Some((_, high)) => output.push(high << 4 | nibble),
+                None => self.high = Some((offset, nibble)),
+            }
+
+            Ok(())
+        }
+
+        fn finish(&mut self) -> Result<(), DecodeError> {
+            match self.high.take() {
+                Some((offset, _)) => Err(DecodeError::InvalidLength { offset }),
+                None => Ok(()),
             }
+        }
+    }
+
+    fn hex_to_nibble(digit: u8) -> u8 {
+        // digit has already been validated as hex
+        match digit {
+            b'0'..=b'9' => digit - b'0',
+            b'a'..=b'f' => digit - b'a' + 10,
+            b'A'..=b'F' => digit - b'A' + 10,
             _ => unreachable!(),
         }
     }
@@ -37,8 +102,7 @@ pub mod hex {
     pub fn encode(input: Vec<u8>) -> String {
         input
             .iter()
-            .map(|x| byte_to_nibble(*x))
-            .flatten()
+            .flat_map(|x| byte_to_nibble(*x))
             .map(nibble_to_hex)
             .collect::<String>()
     }
@@ -48,48 +112,191 @@ pub mod hex {
     }
 
     fn nibble_to_hex(nibble: u8) -> char {
-        match nibble {
-            0..=9 => (nibble + 48) as char,          // index into ascii numbers
-            10..=16 => (nibble + (97 - 10)) as char, // index into char - value in hex
+        // masked to the low 4 bits, so every value is a hex digit
+        match nibble & 0xf {
+            x @ 0..=9 => (x + 48) as char, // index into ascii numbers
+            x => (x + (97 - 10)) as char,  // index into char - value in hex
+        }
+    }
+
+    // Hex encodes everything written to it into the inner writer.
+    pub struct EncoderWriter<W: Write> {
+        inner: W,
+    }
+
+    impl<W: Write> EncoderWriter<W> {
+        pub fn new(inner: W) -> EncoderWriter<W> {
+            EncoderWriter { inner }
+        }
+
+        // Flushes and hands back the inner writer. Every byte encodes on its
+        // own so unlike base64 there is never anything left to write out.
+        pub fn finish(mut self) -> io::Result<W> {
+            self.inner.flush()?;
+            Ok(self.inner)
+        }
+    }
+
+    impl<W: Write> Write for EncoderWriter<W> {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.inner.write_all(encode(buf.to_vec()).as_bytes())?;
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            self.inner.flush()
+        }
+    }
 
-            _ => {
-                println!("Invalid nibble value, freaking out");
-                unreachable!();
+    // Reads hex from the inner reader and yields the decoded bytes. Decode
+    // errors come back as io::ErrorKind::InvalidData wrapping a DecodeError
+    // whose offset counts bytes from the start of the stream.
+    pub struct DecoderReader<R: Read> {
+        inner: R,
+        decoder: DigitDecoder,
+        input: Vec<u8>,
+        output: Vec<u8>,
+        output_pos: usize,
+        offset: usize,
+        finished: bool,
+    }
+
+    impl<R: Read> DecoderReader<R> {
+        pub fn new(inner: R) -> DecoderReader<R> {
+            DecoderReader {
+                inner,
+                decoder: DigitDecoder::new(),
+                input: vec![0; BUFFER_SIZE],
+                output: Vec::with_capacity(BUFFER_SIZE / 2),
+                output_pos: 0,
+                offset: 0,
+                finished: false,
             }
         }
+
+        // Reads the next block from the inner reader and decodes it.
+        fn fill_output(&mut self) -> io::Result<()> {
+            self.output.clear();
+            self.output_pos = 0;
+
+            let read = self.inner.read(&mut self.input)?;
+
+            if read == 0 {
+                self.finished = true;
+                return self.decoder.finish().map_err(invalid_data);
+            }
+
+            for &byte in &self.input[..read] {
+                // the stream isn't necessarily UTF-8, anything outside ASCII is
+                // invalid anyway so report it as the replacement character
+                let symbol = if byte.is_ascii() {
+                    byte as char
+                } else {
+                    char::REPLACEMENT_CHARACTER
+                };
+
+                if let Err(e) = self.decoder.push(self.offset, symbol, &mut self.output) {
+                    self.finished = true;
+                    return Err(invalid_data(e));
+                }
+                self.offset += 1;
+            }
+
+            Ok(())
+        }
+    }
+
+    impl<R: Read> Read for DecoderReader<R> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            // a block of whitespace or a single digit decodes to nothing, so
+            // keep reading until there is output or the stream is done
+            while self.output_pos == self.output.len() {
+                if self.finished {
+                    return Ok(0);
+                }
+                self.fill_output()?;
+            }
+
+            let count = buf.len().min(self.output.len() - self.output_pos);
+            buf[..count].copy_from_slice(&self.output[self.output_pos..self.output_pos + count]);
+            self.output_pos += count;
+
+            Ok(count)
+        }
+    }
+
+    fn invalid_data(error: DecodeError) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, error)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::hex::{decode, encode};
+    use crate::hex::{decode, encode, DecodeError, DecoderReader, EncoderWriter};
+    use std::io::{self, Read, Write};
 
     #[test]
     fn test_hello_world_decode() {
         assert_eq!(
             Vec::<u8>::from("Hello, World!"),
-            decode("48656c6c6f2c20576f726c6421")
+            decode("48656c6c6f2c20576f726c6421").unwrap()
         )
     }
 
     #[test]
     fn test_sentence_decode() {
         assert_eq!(Vec::<u8>::from("With the start of the new year, we can see a new trend delineating Linux software. Phosh with its newbord libadwaita, KDE's Kirigami and Maui Shell show that - like it or not - the era of convergent Linux applications has just started."),
-            decode("5769746820746865207374617274206f6620746865206e657720796561722c2077652063616e207365652061206e6577207472656e642064656c696e656174696e67204c696e757820736f6674776172652e2050686f7368207769746820697473206e6577626f7264206c6962616477616974612c204b44452773204b69726967616d6920616e64204d617569205368656c6c2073686f772074686174202d206c696b65206974206f72206e6f74202d2074686520657261206f6620636f6e76657267656e74204c696e7578206170706c69636174696f6e7320686173206a75737420737461727465642e"));
+            decode("5769746820746865207374617274206f6620746865206e657720796561722c2077652063616e207365652061206e6577207472656e642064656c696e656174696e67204c696e757820736f6674776172652e2050686f7368207769746820697473206e6577626f7264206c6962616477616974612c204b44452773204b69726967616d6920616e64204d617569205368656c6c2073686f772074686174202d206c696b65206974206f72206e6f74202d2074686520657261206f6620636f6e76657267656e74204c696e7578206170706c69636174696f6e7320686173206a75737420737461727465642e").unwrap());
     }
 
     #[test]
     fn test_unicode_decode() {
         assert_eq!(
             Vec::<u8>::from("Ã±tÃ«rnÃ¢tiÃ´nÃ liÅ¾Ã¦tiÃ¸n"),
-            decode("c383c2b174c383c2ab726ec383c2a27469c383c2b46ec383206c69c385c2bec383c2a67469c383c2b86e")
+            decode("c383c2b174c383c2ab726ec383c2a27469c383c2b46ec383206c69c385c2bec383c2a67469c383c2b86e").unwrap()
         )
     }
 
     #[test]
-    #[should_panic]
     fn test_invalid_hexstr_decode() {
-        decode("this ain't valid");
+        assert_eq!(
+            decode("this ain't valid"),
+            Err(DecodeError::InvalidSymbol {
+                offset: 0,
+                symbol: 't'
+            })
+        );
+        assert_eq!(
+            decode("48656c6g"),
+            Err(DecodeError::InvalidSymbol {
+                offset: 7,
+                symbol: 'g'
+            })
+        );
+    }
+
+    #[test]
+    fn test_odd_length_decode() {
+        assert_eq!(
+            decode("48656"),
+            Err(DecodeError::InvalidLength { offset: 4 })
+        );
+    }
+
+    #[test]
+    fn test_trailing_whitespace_decode() {
+        assert_eq!(
+            decode("4865\n"),
+            Err(DecodeError::InvalidSymbol {
+                offset: 4,
+                symbol: '\n'
+            })
+        );
+    }
+
+    #[test]
+    fn test_uppercase_decode() {
+        assert_eq!(Vec::<u8>::from("Hello"), decode("48656C6C6F").unwrap());
     }
 
     #[test]
@@ -115,4 +322,53 @@ mod tests {
             encode(Vec::<u8>::from("Ã±tÃ«rnÃ¢tiÃ´nÃ liÅ¾Ã¦tiÃ¸n"))
         )
     }
+
+    #[test]
+    fn test_encoder_writer() {
+        let mut writer = EncoderWriter::new(Vec::new());
+        for chunk in b"Hello, World!".chunks(3) {
+            writer.write_all(chunk).unwrap();
+        }
+
+        assert_eq!(
+            b"48656c6c6f2c20576f726c6421".to_vec(),
+            writer.finish().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_decoder_reader_partial_reads() {
+        // a one byte buffer splits every pair of digits across reads
+        let mut reader = DecoderReader::new(io::BufReader::with_capacity(
+            1,
+            "48656c6c6f2c20576f726c6421".as_bytes(),
+        ));
+
+        let mut output = Vec::new();
+        reader.read_to_end(&mut output).unwrap();
+
+        assert_eq!(Vec::<u8>::from("Hello, World!"), output);
+    }
+
+    #[test]
+    fn test_decoder_reader_errors() {
+        let mut reader = DecoderReader::new("4865\n6c".as_bytes());
+        let error = reader.read_to_end(&mut Vec::new()).unwrap_err();
+
+        assert_eq!(io::ErrorKind::InvalidData, error.kind());
+        assert_eq!(
+            Some(&DecodeError::InvalidSymbol {
+                offset: 4,
+                symbol: '\n'
+            }),
+            error.get_ref().unwrap().downcast_ref::<DecodeError>()
+        );
+
+        let mut reader = DecoderReader::new("48656".as_bytes());
+        let error = reader.read_to_end(&mut Vec::new()).unwrap_err();
+        assert_eq!(
+            Some(&DecodeError::InvalidLength { offset: 4 }),
+            error.get_ref().unwrap().downcast_ref::<DecodeError>()
+        );
+    }
 }
@@ -0,0 +1,108 @@
+// Statistics report for the analyze subcommand, with a bar chart of letter
+// frequencies against the expected language.
+use english_recognition::language::{detect_language, Language};
+use english_recognition::statistics::{
+    chi_squared, digraphic_index_of_coincidence, entropy_per_byte, entropy_per_char,
+    index_of_coincidence, letter_counts, RANDOM_IC,
+};
+use std::io::{self, Write};
+
+// Characters in the longest bar of the chart.
+const BAR_WIDTH: usize = 50;
+
+// Digraphic index of coincidence of english and of random letter pairs.
+const ENGLISH_DIGRAPHIC_IC: f64 = 0.0069;
+const RANDOM_DIGRAPHIC_IC: f64 = 1.0 / 676.0;
+
+// Language guesses below this confidence are left out of the report.
+const MIN_CONFIDENCE: f64 = 0.01;
+
+pub fn report(out: &mut dyn Write, bytes: &[u8], lang: &Language) -> io::Result<()> {
+    let text = String::from_utf8_lossy(bytes);
+    let counts = letter_counts(&text);
+    let letters = counts.iter().sum::<usize>();
+
+    writeln!(
+        out,
+        "Length: {} bytes, {} characters, {letters} letters",
+        bytes.len(),
+        text.chars().count()
+    )?;
+    writeln!(
+        out,
+        "Shannon entropy: {:.4} bits per byte, {:.4} bits per character",
+        entropy_per_byte(bytes),
+        entropy_per_char(&text)
+    )?;
+    writeln!(
+        out,
+        "Index of coincidence: {:.4} ({} {:.4}, random {RANDOM_IC:.4})",
+        index_of_coincidence(&text),
+        lang.name,
+        lang.index_of_coincidence()
+    )?;
+    writeln!(
+        out,
+        "Digraphic index of coincidence: {:.4} (english {ENGLISH_DIGRAPHIC_IC:.4}, random {RANDOM_DIGRAPHIC_IC:.4})",
+        digraphic_index_of_coincidence(&text)
+    )?;
+
+    if letters == 0 {
+        return writeln!(out, "\nNo letters to compare to {}.", lang.name);
+    }
+
+    let guesses = detect_language(&text)
+        .into_iter()
+        .filter(|(_, confidence)| *confidence >= MIN_CONFIDENCE)
+        .map(|(language, confidence)| format!("{} {:.1}%", language.name, confidence * 100.0))
+        .collect::<Vec<String>>();
+    writeln!(out, "Language: {}", guesses.join(", "))?;
+
+    writeln!(
+        out,
+        "Chi-squared against {}: {:.2}",
+        lang.name,
+        chi_squared(&counts, &lang.frequencies)
+    )?;
+
+    writeln!(
+        out,
+        "\nLetter frequencies, # for the input and | where {} would be:",
+        lang.name
+    )?;
+
+    let frequencies = counts.map(|x| x as f64 / letters as f64);
+    let largest = frequencies
+        .iter()
+        .chain(&lang.frequencies)
+        .fold(0.0_f64, |a, b| a.max(*b));
+
+    for ((letter, frequency), expected) in ('a'..='z').zip(frequencies).zip(lang.frequencies) {
+        let bar = bar(frequency / largest, expected / largest);
+
+        writeln!(
+            out,
+            "{letter} {bar} {:5.2}% ({} {:5.2}%)",
+            frequency * 100.0,
+            lang.name,
+            expected * 100.0
+        )?;
+    }
+
+    Ok(())
+}
+
+// Bar filled to the observed share of its width with a marker at the
+// expected share, both 0 to 1.
+fn bar(observed: f64, expected: f64) -> String {
+    let filled = (observed * BAR_WIDTH as f64).round() as usize;
+    let marker = ((expected * BAR_WIDTH as f64).round() as usize).min(BAR_WIDTH - 1);
+
+    (0..BAR_WIDTH)
+        .map(|x| match x {
+            x if x == marker => '|',
+            x if x < filled => '#',
+            _ => ' ',
+        })
+        .collect()
+}
@@ -0,0 +1,228 @@
+// Crib dragging for xor crib, a one off report or an interactive workbench
+// where recovered keystream bytes are pinned and every plaintext re-rendered.
+// Ciphertexts are numbered from 1 for people.
+use hex::hex;
+use std::io::{self, BufRead, Write};
+use xor::xor;
+
+const HELP: &str = "\
+crib TEXT               drag TEXT over every pair of ciphertexts
+pin N OFFSET TEXT       ciphertext N reads TEXT from OFFSET, pinning the keystream
+key OFFSET HEX          pin keystream bytes from OFFSET
+unpin OFFSET [LENGTH]   forget LENGTH keystream bytes from OFFSET, 1 by default
+show                    show the keystream and plaintexts
+help                    show this
+quit                    leave";
+
+// Best crib positions, plausible english marked with a *.
+pub fn report(
+    out: &mut dyn Write,
+    ciphertexts: &[Vec<u8>],
+    crib: &[u8],
+    top: usize,
+) -> io::Result<()> {
+    let matches = xor::drag_crib(ciphertexts, crib);
+    let plausible = matches.iter().filter(|x| x.is_plausible()).count();
+
+    writeln!(
+        out,
+        "Dragged {:?} over {} offsets, {plausible} plausible:\n",
+        String::from_utf8_lossy(crib),
+        matches.len()
+    )?;
+
+    for crib_match in matches.iter().take(top) {
+        writeln!(
+            out,
+            "{}{:.4}\t{}^{}\toffset {}\t{}",
+            if crib_match.is_plausible() { "*" } else { " " },
+            crib_match.score,
+            crib_match.pair.0 + 1,
+            crib_match.pair.1 + 1,
+            crib_match.offset,
+            render(crib_match.text.iter().copied().map(Some))
+        )?;
+    }
+
+    Ok(())
+}
+
+// Read commands until quit or the end of the input.
+pub fn interactive(
+    workbench: &mut xor::Workbench,
+    input: &mut dyn BufRead,
+    out: &mut dyn Write,
+    top: usize,
+) -> io::Result<()> {
+    writeln!(out, "{HELP}\n")?;
+    show(out, workbench)?;
+
+    let mut line = String::new();
+    loop {
+        write!(out, "> ")?;
+        out.flush()?;
+
+        line.clear();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(());
+        }
+
+        let line = line.trim_end_matches(['\r', '\n']);
+        let (command, rest) = line.split_once(' ').unwrap_or((line, ""));
+
+        let result = match command {
+            "" => Ok(()),
+            "quit" | "exit" => return Ok(()),
+            "help" => writeln!(out, "{HELP}"),
+            "show" => show(out, workbench),
+            "crib" => report(out, workbench.ciphertexts(), rest.as_bytes(), top),
+            "pin" => match pin(workbench, rest) {
+                Ok(()) => show(out, workbench),
+                Err(e) => writeln!(out, "{e}"),
+            },
+            "key" => match key(workbench, rest) {
+                Ok(()) => show(out, workbench),
+                Err(e) => writeln!(out, "{e}"),
+            },
+            "unpin" => match unpin(workbench, rest) {
+                Ok(()) => show(out, workbench),
+                Err(e) => writeln!(out, "{e}"),
+            },
+            command => writeln!(out, "unknown command {command:?}, try help"),
+        };
+        result?;
+    }
+}
+
+fn pin(workbench: &mut xor::Workbench, args: &str) -> Result<(), String> {
+    let mut args = args.splitn(3, ' ');
+    let (Some(index), Some(offset), Some(text)) = (args.next(), args.next(), args.next()) else {
+        return Err("usage: pin N OFFSET TEXT".to_string());
+    };
+
+    let index = index
+        .parse::<usize>()
+        .ok()
+        .and_then(|x| x.checked_sub(1))
+        .ok_or(format!("{index:?} isn't a ciphertext number"))?;
+
+    match workbench.pin_plaintext(index, offset_arg(offset)?, text.as_bytes()) {
+        true => Ok(()),
+        false => Err(format!("there is no ciphertext {}", index + 1)),
+    }
+}
+
+fn key(workbench: &mut xor::Workbench, args: &str) -> Result<(), String> {
+    let Some((offset, key)) = args.split_once(' ') else {
+        return Err("usage: key OFFSET HEX".to_string());
+    };
+
+    let key = hex::decode(key.trim()).map_err(|e| e.to_string())?;
+    workbench.pin_key(offset_arg(offset)?, &key);
+
+    Ok(())
+}
+
+fn unpin(workbench: &mut xor::Workbench, args: &str) -> Result<(), String> {
+    let mut args = args.split_whitespace();
+    let offset = offset_arg(args.next().unwrap_or_default())?;
+    let length = match args.next() {
+        Some(length) => length
+            .parse()
+            .map_err(|_| format!("{length:?} isn't a length"))?,
+        None => 1,
+    };
+
+    workbench.unpin(offset, length);
+
+    Ok(())
+}
+
+fn offset_arg(offset: &str) -> Result<usize, String> {
+    offset
+        .parse()
+        .map_err(|_| format!("{offset:?} isn't an offset"))
+}
+
+fn show(out: &mut dyn Write, workbench: &xor::Workbench) -> io::Result<()> {
+    let keystream = workbench
+        .keystream()
+        .iter()
+        .map(|x| x.map_or("..".to_string(), |x| format!("{x:02x}")))
+        .collect::<String>();
+    writeln!(out, "key\t{keystream}")?;
+
+    for (i, plaintext) in workbench.plaintexts().into_iter().enumerate() {
+        writeln!(out, "{}\t{}", i + 1, render(plaintext.into_iter()))?;
+    }
+
+    Ok(())
+}
+
+// Known printable bytes as they are, unknown ones as _ and the rest as .
+fn render(bytes: impl Iterator<Item = Option<u8>>) -> String {
+    bytes
+        .map(|x| match x {
+            None => '_',
+            Some(x) if x == b' ' || x.is_ascii_graphic() => x as char,
+            Some(_) => '.',
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::crib::{interactive, render};
+    use xor::xor;
+
+    const KEYSTREAM: &[u8] = b"\x8f\x1d\x5a\xc3\x07\x99\x4e\x20\x6b\xd2\x13\x77\xa4\x58";
+
+    fn workbench() -> xor::Workbench {
+        xor::Workbench::new(vec![
+            xor::xor_bytes(b"meet me at ten", KEYSTREAM),
+            xor::xor_bytes(b"attack at dawn", KEYSTREAM),
+        ])
+    }
+
+    fn run(commands: &str) -> String {
+        let mut out = Vec::new();
+        interactive(&mut workbench(), &mut commands.as_bytes(), &mut out, 3).unwrap();
+
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_pin_renders_every_plaintext() {
+        let out = run("pin 2 0 attack\nquit\nshow\n");
+
+        assert!(out.contains("1\tmeet m________\n"));
+        assert!(out.contains("2\tattack________\n"));
+        // nothing after quit runs
+        assert_eq!(1, out.matches("1\tmeet m").count());
+    }
+
+    #[test]
+    fn test_key_and_unpin() {
+        let out = run("key 0 8f1d\nunpin 1\n");
+
+        assert!(out.contains("key\t8f1d........"));
+        assert!(out.contains("key\t8f.........."));
+        assert!(out.contains("1\tm_____________\n"));
+    }
+
+    #[test]
+    fn test_crib_and_errors() {
+        let out = run("crib  at \npin 3 0 x\nkey 0 zz\nunpin\nfrobnicate\n");
+
+        assert!(out.contains("Dragged \" at \" over 11 offsets"));
+        assert!(out.contains("there is no ciphertext 3"));
+        assert!(out.contains("invalid hex symbol 'z'"));
+        assert!(out.contains("\"\" isn't an offset"));
+        assert!(out.contains("unknown command \"frobnicate\""));
+    }
+
+    #[test]
+    fn test_render() {
+        assert_eq!("a_.", render([Some(b'a'), None, Some(0)].into_iter()));
+    }
+}
@@ -0,0 +1,210 @@
+// Shared input handling for every subcommand. Data comes from the positional
+// argument, a file or stdin, optionally hex or base64 decoded on the way in.
+use base64::base64;
+use clap::{Arg, ArgMatches};
+use hex::hex;
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+
+use crate::{exit_with_error, report_decode_error};
+
+// Lenient so wrapped base64 files can be fed straight in.
+const INPUT_BASE64: base64::Engine = base64::Engine::new(
+    base64::STANDARD_ALPHABET,
+    base64::Config::new()
+        .with_padding(base64::PaddingMode::Optional)
+        .with_lenient(true),
+);
+
+pub fn args(help: &'static str) -> Vec<Arg<'static, 'static>> {
+    vec![
+        Arg::with_name("input")
+            .help(help)
+            .long_help("Data to work on, '-' or leaving it off reads stdin"),
+        Arg::with_name("file")
+            .long("file")
+            .takes_value(true)
+            .value_name("PATH")
+            .conflicts_with("input")
+            .help("Read input from a file"),
+        Arg::with_name("in-hex")
+            .long("in-hex")
+            .conflicts_with("in-b64")
+            .help("Input is hex, decode it first"),
+        Arg::with_name("in-b64")
+            .long("in-b64")
+            .help("Input is base64, decode it first"),
+    ]
+}
+
+// Same with any number of inputs as arguments, e.g. several ciphertexts. A
+// file or stdin holds one per line instead.
+pub fn multiple_args(help: &'static str) -> Vec<Arg<'static, 'static>> {
+    let mut args = args(help);
+    args[0] = args[0].clone().multiple(true);
+
+    args
+}
+
+// The positional argument when it can be used as is, so decoders can point at
+// errors in it.
+pub fn literal<'a>(matches: &'a ArgMatches) -> Option<&'a str> {
+    if matches.is_present("in-hex") || matches.is_present("in-b64") {
+        return None;
+    }
+
+    matches.value_of("input").filter(|x| *x != "-")
+}
+
+// Stream the input, decoding it on the fly when asked to.
+pub fn reader(matches: &ArgMatches) -> Box<dyn Read> {
+    // literal arguments are small, decode them up front so errors get a caret
+    if let Some(input) = matches.value_of("input").filter(|x| *x != "-") {
+        if literal(matches).is_none() {
+            return Box::new(io::Cursor::new(decode_literal(matches, input)));
+        }
+    }
+
+    let source = source(matches);
+
+    if matches.is_present("in-hex") {
+        Box::new(hex::DecoderReader::new(TrimEnd::new(source)))
+    } else if matches.is_present("in-b64") {
+        Box::new(base64::DecoderReader::new(source, INPUT_BASE64))
+    } else {
+        source
+    }
+}
+
+// The input as given, before any decoding.
+fn source(matches: &ArgMatches) -> Box<dyn Read> {
+    match (matches.value_of("input"), matches.value_of("file")) {
+        (_, Some(path)) => match File::open(path) {
+            Ok(file) => Box::new(BufReader::new(file)),
+            Err(e) => exit_with_error(format!("couldn't open {path}: {e}")),
+        },
+        (Some(input), None) if input != "-" => Box::new(io::Cursor::new(input.to_string())),
+        _ => Box::new(io::stdin().lock()),
+    }
+}
+
+// The whole input as bytes.
+pub fn read_bytes(matches: &ArgMatches) -> Vec<u8> {
+    let mut input = Vec::new();
+    if let Err(e) = reader(matches).read_to_end(&mut input) {
+        exit_with_error(e);
+    }
+
+    input
+}
+
+// Drops trailing whitespace, like the newline ending a file or piped echo, so
+// decoders that reject whitespace can read streams.
+pub struct TrimEnd<R: Read> {
+    inner: R,
+    // whitespace held back until data follows it
+    pending: Vec<u8>,
+    ready: Vec<u8>,
+    position: usize,
+}
+
+impl<R: Read> TrimEnd<R> {
+    pub fn new(inner: R) -> TrimEnd<R> {
+        TrimEnd {
+            inner,
+            pending: Vec::new(),
+            ready: Vec::new(),
+            position: 0,
+        }
+    }
+}
+
+impl<R: Read> Read for TrimEnd<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut chunk = [0; 8192];
+
+        while self.position == self.ready.len() {
+            let read = self.inner.read(&mut chunk)?;
+            if read == 0 {
+                return Ok(0);
+            }
+
+            match chunk[..read].iter().rposition(|x| !x.is_ascii_whitespace()) {
+                Some(last) => {
+                    self.ready = std::mem::take(&mut self.pending);
+                    self.ready.extend_from_slice(&chunk[..=last]);
+                    self.pending.extend_from_slice(&chunk[last + 1..read]);
+                    self.position = 0;
+                }
+                None => self.pending.extend_from_slice(&chunk[..read]),
+            }
+        }
+
+        let len = buf.len().min(self.ready.len() - self.position);
+        buf[..len].copy_from_slice(&self.ready[self.position..self.position + len]);
+        self.position += len;
+
+        Ok(len)
+    }
+}
+
+fn decode_literal(matches: &ArgMatches, input: &str) -> Vec<u8> {
+    decode_line(matches, input).unwrap_or_else(|(offset, e)| report_decode_error(input, offset, e))
+}
+
+// One line of encoded input, base64 with --in-b64 and hex otherwise, or the
+// offset it went wrong at with the error.
+pub fn decode_line(matches: &ArgMatches, line: &str) -> Result<Vec<u8>, (usize, String)> {
+    match matches.is_present("in-b64") {
+        true => INPUT_BASE64
+            .decode(line)
+            .map_err(|e| (e.offset(), e.to_string())),
+        false => hex::decode(line).map_err(|e| (e.offset(), e.to_string())),
+    }
+}
+
+// Each input from multiple_args as a line of text, the arguments themselves
+// or the lines of a file or stdin. Lines are left encoded for decode_line, a
+// stream decoder would run them all together.
+pub fn read_lines(matches: &ArgMatches) -> Vec<String> {
+    match matches.values_of("input") {
+        Some(values) if matches.value_of("input") != Some("-") => {
+            values.map(str::to_string).collect()
+        }
+        _ => {
+            let mut input = Vec::new();
+            if let Err(e) = source(matches).read_to_end(&mut input) {
+                exit_with_error(e);
+            }
+
+            String::from_utf8_lossy(&input)
+                .lines()
+                .map(str::to_string)
+                .collect()
+        }
+    }
+}
+
+// The whole input as text, invalid UTF-8 is replaced rather than rejected.
+pub fn read_text(matches: &ArgMatches) -> String {
+    match literal(matches) {
+        Some(input) => input.to_string(),
+        None => String::from_utf8_lossy(&read_bytes(matches)).into_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trim_end() {
+        // one byte reads hold whitespace back across many calls
+        let inner = io::BufReader::with_capacity(1, "4865\n 6c\r\n\n".as_bytes());
+
+        let mut output = Vec::new();
+        TrimEnd::new(inner).read_to_end(&mut output).unwrap();
+
+        assert_eq!(b"4865\n 6c".to_vec(), output);
+    }
+}
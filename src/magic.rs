@@ -0,0 +1,240 @@
+// Guess how a blob was encoded by trying every decoder on it, and on whatever
+// they produce, then ranking the results by how much they look like english.
+use english_recognition::scorer::{descending, Scorer};
+use std::collections::HashMap;
+
+use crate::operation::{FromBase64, FromHex, Rot, Step, Xor};
+use crate::recipe::Recipe;
+
+// Only the start of each result is scored, it's plenty to tell text apart.
+const SAMPLE_SIZE: usize = 512;
+
+// Fraction of printable bytes a rotation or XOR result needs to be followed,
+// and any result needs to rank as text.
+const MIN_PRINTABLE: f64 = 0.95;
+
+pub struct Candidate {
+    pub recipe: Recipe,
+    pub output: Vec<u8>,
+    pub score: f64,
+    printable: bool,
+}
+
+// Every chain of up to depth operations that applies to the input, best first.
+// Printable results always come before binary ones so scorers that only look
+// at letters aren't fooled, and ties go to the shorter chain.
+pub fn search(input: &[u8], depth: usize, scorer: &dyn Scorer) -> Vec<Candidate> {
+    let mut search = Search::new(scorer);
+
+    search.explore(input.to_vec(), Vec::new(), depth);
+    let mut candidates = search.candidates;
+
+    candidates.sort_by(|a, b| {
+        b.printable
+            .cmp(&a.printable)
+            .then(descending(a.score, b.score))
+            .then(a.recipe.steps.len().cmp(&b.recipe.steps.len()))
+    });
+
+    candidates
+}
+
+// Score the start of the bytes, text scorers see invalid UTF-8 replaced.
+pub fn score(bytes: &[u8], scorer: &dyn Scorer) -> f64 {
+    let sample = &bytes[..bytes.len().min(SAMPLE_SIZE)];
+
+    scorer.score_bytes(sample)
+}
+
+fn printable_ratio(bytes: &[u8]) -> f64 {
+    if bytes.is_empty() {
+        return 0.0;
+    }
+
+    let printable = bytes
+        .iter()
+        .filter(|x| x.is_ascii_graphic() || x.is_ascii_whitespace())
+        .count();
+
+    printable as f64 / bytes.len() as f64
+}
+
+struct Search<'a> {
+    scorer: &'a dyn Scorer,
+    // most depth left each result has been expanded with, split by whether
+    // its chain has used up its one rotation or XOR
+    expanded: HashMap<(Vec<u8>, bool), usize>,
+    // where each result is in candidates
+    found: HashMap<Vec<u8>, usize>,
+    candidates: Vec<Candidate>,
+}
+
+impl Search<'_> {
+    fn new(scorer: &dyn Scorer) -> Search<'_> {
+        Search {
+            scorer,
+            expanded: HashMap::new(),
+            found: HashMap::new(),
+            candidates: Vec::new(),
+        }
+    }
+
+    // Depth first, so a result can be reached at the end of a long chain
+    // before a shorter one. The shorter chain expands it again with the depth
+    // it has left.
+    fn explore(&mut self, data: Vec<u8>, steps: Vec<Step>, depth: usize) {
+        if data.is_empty() {
+            return;
+        }
+
+        let key = (data, steps.iter().any(is_cipher));
+        if self.expanded.get(&key).is_some_and(|x| *x >= depth) {
+            return;
+        }
+        self.expanded.insert(key.clone(), depth);
+        let (data, _) = key;
+
+        if depth > 0 {
+            for step in next_steps(&steps) {
+                let cipher = is_cipher(&step);
+
+                let output = match step.operation().run(&data) {
+                    Ok(output) => output,
+                    Err(_) => continue,
+                };
+
+                // ciphers apply to anything, only follow the ones that give text
+                if cipher && printable_ratio(&output) < MIN_PRINTABLE {
+                    continue;
+                }
+
+                let mut chain = steps.clone();
+                chain.push(step);
+                self.explore(output, chain, depth - 1);
+            }
+        }
+
+        // found again, keep the shorter chain to it
+        if let Some(&index) = self.found.get(&data) {
+            let candidate = &mut self.candidates[index];
+            if steps.len() < candidate.recipe.steps.len() {
+                candidate.recipe = Recipe { steps };
+            }
+            return;
+        }
+
+        let sample = &data[..data.len().min(SAMPLE_SIZE)];
+
+        self.found.insert(data.clone(), self.candidates.len());
+        self.candidates.push(Candidate {
+            score: score(sample, self.scorer),
+            printable: printable_ratio(sample) >= MIN_PRINTABLE,
+            recipe: Recipe { steps },
+            output: data,
+        });
+    }
+}
+
+fn is_cipher(step: &Step) -> bool {
+    matches!(step, Step::Rot(_) | Step::Xor(_))
+}
+
+// Decoders are always worth a try as they fail fast on the wrong input. A
+// chain gets at most one rotation or XOR, otherwise they'd stack endlessly.
+fn next_steps(steps: &[Step]) -> Vec<Step> {
+    let mut next = ["standard", "url"]
+        .iter()
+        .map(|alphabet| {
+            Step::FromBase64(FromBase64 {
+                alphabet: alphabet.to_string(),
+            })
+        })
+        .collect::<Vec<Step>>();
+    next.push(Step::FromHex(FromHex));
+
+    if !steps.iter().any(is_cipher) {
+        next.extend((1..26).map(|shift| Step::Rot(Rot { shift })));
+        next.extend((1..=255).map(|key| Step::Xor(Xor { key })));
+    }
+
+    next
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::magic::{score, search, Search};
+    use crate::operation::{FromBase64, Step};
+    use crate::recipe::Recipe;
+    use english_recognition::scorer::Composite;
+
+    const TEXT: &str = "It is a truth universally acknowledged, that a single man in possession of a good fortune, must be in want of a wife.";
+
+    #[test]
+    fn test_finds_layered_encoding() {
+        let encode = "rot 13 | to_hex | to_base64".parse::<Recipe>().unwrap();
+        let blob = encode.run(TEXT.as_bytes().to_vec()).unwrap();
+
+        let best = &search(&blob, 3, &Composite::english())[0];
+
+        assert_eq!(TEXT.as_bytes(), &best.output[..]);
+        assert_eq!("from_base64 | from_hex | rot 13", best.recipe.to_string());
+    }
+
+    #[test]
+    fn test_finds_single_byte_xor() {
+        let encode = "xor 0x58 | to_hex".parse::<Recipe>().unwrap();
+        let blob = encode.run(TEXT.as_bytes().to_vec()).unwrap();
+
+        let best = &search(&blob, 2, &Composite::english())[0];
+
+        assert_eq!(TEXT.as_bytes(), &best.output[..]);
+        assert_eq!("from_hex | xor 0x58", best.recipe.to_string());
+    }
+
+    #[test]
+    fn test_shorter_chain_expands_again() {
+        let scorer = Composite::english();
+        let blob = "to_hex"
+            .parse::<Recipe>()
+            .unwrap()
+            .run(TEXT.as_bytes().to_vec())
+            .unwrap();
+        let long = vec![
+            Step::FromBase64(FromBase64 {
+                alphabet: "standard".to_string(),
+            });
+            2
+        ];
+
+        // reached first at the end of a chain with no depth left
+        let mut search = Search::new(&scorer);
+        search.explore(blob.clone(), long, 0);
+        assert_eq!(1, search.candidates.len());
+
+        search.explore(blob.clone(), Vec::new(), 1);
+
+        let found = |output: &[u8]| {
+            let candidate = search.candidates.iter().find(|x| x.output == output);
+            candidate.map(|x| x.recipe.to_string())
+        };
+        assert_eq!(Some(String::new()), found(&blob));
+        assert_eq!(Some("from_hex".to_string()), found(TEXT.as_bytes()));
+    }
+
+    #[test]
+    fn test_plain_text_needs_no_decoding() {
+        let best = &search(TEXT.as_bytes(), 2, &Composite::english())[0];
+
+        assert!(best.recipe.steps.is_empty());
+    }
+
+    #[test]
+    fn test_score_prefers_text() {
+        let scorer = Composite::english();
+
+        assert!(
+            score(TEXT.as_bytes(), &scorer) > score(&[0x9c, 0x01, 0xfe, 0x41, 0x7f, 0x00], &scorer)
+        );
+        assert_eq!(0.0, score(b"", &scorer));
+    }
+}
@@ -1,8 +1,36 @@
 use base64::base64;
-use caesar::caesar::brute_force;
-use clap::{App, AppSettings, Arg, SubCommand};
-use english_recognition::frequency_analysis::score_strings;
+use caesar::affine;
+use caesar::caesar::{brute_force_keywords, Alphabet, Keyed, KeyedCandidate};
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use english_recognition::frequency_analysis::score_strings_with;
+use english_recognition::ngram::{self, NgramModel};
+use english_recognition::scorer::descending;
+use english_recognition::segment;
 use hex::hex;
+use std::fmt::Display;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::process;
+use xor::xor;
+
+// Characters of context to show either side of an error position.
+const DIAGNOSTIC_CONTEXT: usize = 32;
+
+// Characters of each result shown in ranked reports, --raw gives it all.
+const PREVIEW: usize = 72;
+
+mod analyze;
+mod crib;
+mod input;
+mod magic;
+mod operation;
+mod output;
+mod recipe;
+mod scoring;
+
+use output::{Format, Output};
+use recipe::Recipe;
 
 //https://github.com/clap-rs/clap/blob/v3.0.12/examples/tutorial_builder/03_04_subcommands.rs
 fn main() {
@@ -12,14 +40,98 @@ fn main() {
         .setting(AppSettings::SubcommandRequiredElseHelp)
         .subcommand(
             SubCommand::with_name("caesar")
-                .about("String to solve for caesar cipher")
+                .about("Caesar cipher, shifting letters along the alphabet")
                 .help("Solver for substitution cipher using bruteforcing")
-                .arg(
-                    Arg::with_name("input")
-                        .help("string to bruteforce")
-                        .required(true),
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(
+                    SubCommand::with_name("crack")
+                        .help("Brute forces every shift, or every keyword and shift with --wordlist, ranking the plaintexts")
+                        .args(&input::args("string to bruteforce"))
+                        .args(&output::args())
+                        .args(&scoring::args("frequency"))
+                        .args(&caesar_args())
+                        .arg(
+                            Arg::with_name("wordlist")
+                                .long("wordlist")
+                                .takes_value(true)
+                                .value_name("PATH")
+                                .conflicts_with("keyword")
+                                .help("Brute force keyed caesar with every keyword in a file, one per line"),
+                        )
+                        .arg(top_arg("Keyword and shift pairs to show with --wordlist")),
+                )
+                .subcommand(
+                    SubCommand::with_name("encrypt")
+                        .help("Shifts the input along the alphabet, keyed by --keyword if given")
+                        .args(&input::args("Text to encrypt"))
+                        .args(&output::args())
+                        .args(&caesar_args())
+                        .arg(shift_arg()),
+                )
+                .subcommand(
+                    SubCommand::with_name("decrypt")
+                        .help("Shifts the input back along the alphabet, keyed by --keyword if given")
+                        .args(&input::args("Text to decrypt"))
+                        .args(&output::args())
+                        .args(&caesar_args())
+                        .arg(shift_arg()),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("affine")
+                .about("Affine cipher, caesar with a multiplier")
+                .help("Brute forces all 312 affine keys E(x) = ax + b mod 26, or encrypts, decrypts and recovers keys")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(
+                    SubCommand::with_name("crack")
+                        .help("Brute forces all 312 keys, ranking the plaintexts")
+                        .args(&input::args("string to bruteforce"))
+                        .args(&output::args())
+                        .args(&scoring::args("frequency"))
+                        .arg(top_arg("Keys to show")),
+                )
+                .subcommand(
+                    SubCommand::with_name("encrypt")
+                        .help("Encrypts the input with E(x) = ax + b mod 26")
+                        .args(&input::args("Text to encrypt"))
+                        .args(&output::args())
+                        .args(&affine_key_args()),
+                )
+                .subcommand(
+                    SubCommand::with_name("decrypt")
+                        .help("Decrypts the input with D(y) = a^-1(y - b) mod 26")
+                        .args(&input::args("Text to decrypt"))
+                        .args(&output::args())
+                        .args(&affine_key_args()),
+                )
+                .subcommand(
+                    SubCommand::with_name("recover")
+                        .help("Finds the keys that encrypt known plaintext letters to their ciphertext, two letters are usually enough")
+                        .arg(
+                            Arg::with_name("plain")
+                                .long("plain")
+                                .takes_value(true)
+                                .required(true)
+                                .help("Known plaintext letters, e.g. th"),
+                        )
+                        .arg(
+                            Arg::with_name("cipher")
+                                .long("cipher")
+                                .takes_value(true)
+                                .required(true)
+                                .help("The ciphertext letters they became, in the same order"),
+                        )
+                        .args(&output::args()),
                 ),
         )
+        .subcommand(
+            SubCommand::with_name("analyze")
+                .about("Frequency statistics for cryptanalysis")
+                .help("Reports entropy, index of coincidence, likely languages, chi-squared and letter frequencies against a language")
+                .args(&input::args("Data to analyze"))
+                .args(&output::args())
+                .arg(scoring::lang_arg()),
+        )
         .subcommand(
             SubCommand::with_name("base64")
                 .about("Utilities for interacting with base64 encodings")
@@ -27,12 +139,57 @@ fn main() {
                 .subcommand(
                     SubCommand::with_name("decode")
                         .help("Decodes entered string as base64")
-                        .arg(Arg::with_name("input").help("Data to decode")),
+                        .args(&input::args("Data to decode"))
+                        .args(&output::args())
+                        .arg(base64_alphabet_arg())
+                        .arg(
+                            Arg::with_name("padding")
+                                .long("padding")
+                                .takes_value(true)
+                                .possible_values(&["required", "optional", "forbidden"])
+                                .default_value("required")
+                                .help("Whether trailing '=' padding must be present"),
+                        )
+                        .arg(
+                            Arg::with_name("strict")
+                                .long("strict")
+                                .help("Reject non-canonical encodings with stray trailing bits"),
+                        )
+                        .arg(
+                            Arg::with_name("lenient").long("lenient").help(
+                                "Skip whitespace and line breaks, e.g. in PEM or MIME bodies",
+                            ),
+                        ),
                 )
                 .subcommand(
                     SubCommand::with_name("encode")
                         .help("Encodes entered bytes as base64")
-                        .arg(Arg::with_name("input").help("Data to encode")),
+                        .args(&input::args("Data to encode"))
+                        .args(&output::args())
+                        .arg(base64_alphabet_arg())
+                        .arg(
+                            Arg::with_name("no-pad")
+                                .long("no-pad")
+                                .help("Leave off trailing '=' padding"),
+                        )
+                        .arg(
+                            Arg::with_name("wrap")
+                                .long("wrap")
+                                .takes_value(true)
+                                .validator(|x| match x.parse::<usize>() {
+                                    Ok(_) => Ok(()),
+                                    Err(_) => Err("wrap must be a number of columns".to_string()),
+                                })
+                                .help("Wrap lines at N columns, 64 for PEM, 76 for MIME"),
+                        )
+                        .arg(
+                            Arg::with_name("line-ending")
+                                .long("line-ending")
+                                .takes_value(true)
+                                .possible_values(&["lf", "crlf"])
+                                .default_value("lf")
+                                .help("Line ending used when wrapping"),
+                        ),
                 ),
         )
         .subcommand(
@@ -41,12 +198,196 @@ fn main() {
                 .subcommand(
                     SubCommand::with_name("decode")
                         .help("Enter hex string to decode")
-                        .arg(Arg::with_name("input").help("Hex data to decode")),
+                        .args(&input::args("Hex data to decode"))
+                        .args(&output::args()),
                 )
                 .subcommand(
                     SubCommand::with_name("encode")
                         .help("Enter string or data to encode")
-                        .arg(Arg::with_name("input").help("Data to encode")),
+                        .args(&input::args("Data to encode"))
+                        .args(&output::args()),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("magic")
+                .about("Guess how data was encoded by trying every decoder")
+                .help("Recursively tries base64, hex, rotations and single byte XOR, ranking the results by englishness")
+                .args(&input::args("Data to decode"))
+                .args(&output::args())
+                .args(&scoring::args("composite"))
+                .arg(
+                    Arg::with_name("depth")
+                        .long("depth")
+                        .takes_value(true)
+                        .default_value("3")
+                        .validator(|x| match x.parse::<usize>() {
+                            Ok(_) => Ok(()),
+                            Err(_) => Err("depth must be a number of operations".to_string()),
+                        })
+                        .help("Longest chain of operations to try"),
+                )
+                .arg(top_arg("How many of the best decodings to show")),
+        )
+        .subcommand(
+            SubCommand::with_name("recipe")
+                .about("Run data through a chain of operations")
+                .help("Runs a recipe such as --recipe \"from_base64 | from_hex | rot 13\" over the input")
+                .arg(
+                    Arg::with_name("recipe")
+                        .long("recipe")
+                        .takes_value(true)
+                        .value_name("OPERATIONS")
+                        .required_unless("load")
+                        .conflicts_with("load")
+                        .help("Operations separated by '|': from_base64, to_base64, from_hex, to_hex, rot, xor"),
+                )
+                .args(&input::args("Data to run the recipe over"))
+                .args(&output::args())
+                .arg(
+                    Arg::with_name("load")
+                        .long("load")
+                        .takes_value(true)
+                        .value_name("PATH")
+                        .help("Read the recipe from a .json, .toml or text file"),
+                )
+                .arg(
+                    Arg::with_name("save")
+                        .long("save")
+                        .takes_value(true)
+                        .value_name("PATH")
+                        .help("Write the recipe to a .json, .toml or text file instead of running it"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("xor")
+                .about("Break XOR ciphers")
+                .help("Brute forces XOR keys, ranking the plaintexts by how much they look like english")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(
+                    SubCommand::with_name("single")
+                        .help("Tries every single byte key, --in-hex or --in-b64 for encoded ciphertext")
+                        .args(&input::args("Ciphertext to brute force"))
+                        .args(&output::args())
+                        .args(&scoring::args("bytes"))
+                        .arg(top_arg("How many of the best keys to show")),
+                )
+                .subcommand(
+                    SubCommand::with_name("encrypt")
+                        .help("XORs the input with a key repeated along it, which also decrypts")
+                        .args(&input::args("Data to encrypt"))
+                        .args(&output::args())
+                        .arg(
+                            Arg::with_name("key")
+                                .long("key")
+                                .takes_value(true)
+                                .required(true)
+                                .validator(|x| match x.is_empty() {
+                                    true => Err("key can't be empty".to_string()),
+                                    false => Ok(()),
+                                })
+                                .help("Key to repeat, as text unless --hex-key"),
+                        )
+                        .arg(
+                            Arg::with_name("hex-key")
+                                .long("hex-key")
+                                .help("Key is hex, e.g. 0badf00d"),
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name("break")
+                        .help("Finds the key size of repeating key XOR then solves each key byte as single byte XOR")
+                        .args(&input::args("Ciphertext to break"))
+                        .args(&scoring::args("bytes"))
+                        .args(&output::args())
+                        .arg(
+                            Arg::with_name("max-key-size")
+                                .long("max-key-size")
+                                .takes_value(true)
+                                .default_value("40")
+                                .validator(|x| match x.parse::<usize>() {
+                                    Ok(1..) => Ok(()),
+                                    _ => Err("max key size must be a number above 0".to_string()),
+                                })
+                                .help("Longest key to consider"),
+                        )
+                        .arg(top_arg("How many key size estimates to show")),
+                )
+                .subcommand(
+                    SubCommand::with_name("detect")
+                        .help("Brute forces every line of a file to find the one XORed with a single byte")
+                        .args(&input::args("Hex lines, or base64 with --b64"))
+                        .arg(
+                            Arg::with_name("b64")
+                                .long("b64")
+                                .help("Lines are base64 rather than hex"),
+                        )
+                        .args(&output::args())
+                        .args(&scoring::args("bytes"))
+                        .arg(top_arg("How many of the best lines to show")),
+                )
+                .subcommand(
+                    SubCommand::with_name("crib")
+                        .help("XORs ciphertexts that reused a keystream and drags a crib over them")
+                        .args(&input::multiple_args(
+                            "Hex ciphertexts that share a keystream, or base64 with --in-b64, one per line from a file or stdin",
+                        ))
+                        .args(&output::args())
+                        .arg(
+                            Arg::with_name("crib")
+                                .long("crib")
+                                .takes_value(true)
+                                .value_name("TEXT")
+                                .required_unless("interactive")
+                                .help("Text guessed to be in one of the plaintexts, e.g. \" the \""),
+                        )
+                        .arg(
+                            Arg::with_name("interactive")
+                                .long("interactive")
+                                .short("i")
+                                .conflicts_with_all(&["crib", "out"])
+                                .help("Drag cribs and pin keystream bytes from a prompt, crib is one of its commands"),
+                        )
+                        .arg(top_arg("How many of the best crib positions to show").default_value("10")),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("model")
+                .about("Build n-gram models for scoring")
+                .help("Trains letter n-gram models from your own text to load with --model")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(
+                    SubCommand::with_name("train")
+                        .help("Counts runs of letters in a corpus and saves them as a model")
+                        .arg(
+                            Arg::with_name("corpus")
+                                .long("corpus")
+                                .takes_value(true)
+                                .value_name("PATH")
+                                .required(true)
+                                .help("Text to learn from, anything but letters is skipped"),
+                        )
+                        .arg(
+                            Arg::with_name("order")
+                                .long("order")
+                                .takes_value(true)
+                                .default_value("4")
+                                .validator(|x| match x.parse::<usize>() {
+                                    Ok(1..=ngram::MAX_ORDER) => Ok(()),
+                                    _ => Err(format!(
+                                        "order must be a number from 1 to {}",
+                                        ngram::MAX_ORDER
+                                    )),
+                                })
+                                .help("Longest run of letters to count, 1 for single letters up to 4 for quadgrams"),
+                        )
+                        .arg(
+                            Arg::with_name("out")
+                                .long("out")
+                                .takes_value(true)
+                                .value_name("PATH")
+                                .required(true)
+                                .help("Where to save the model"),
+                        ),
                 ),
         )
         .get_matches();
@@ -54,46 +395,235 @@ fn main() {
     // Select Cryptotop Utility to execute based on commandline args.
     match args.subcommand() {
         // HANDLE CAESAR SUB COMMANDS
-        ("caesar", Some(sub_matches)) => {
-            // bruteforce string
-            let res = brute_force(sub_matches.value_of("input").unwrap());
-
-            // order string by most likely english lang string
-            let res = score_strings(res);
-
-            println!(
-                "Brute force results for: {}\n",
-                sub_matches.value_of("input").unwrap()
-            );
-
-            println!("Ordered by english language frequency score:");
-            let mut first = true;
-            for (can, score) in res {
-                if first {
-                    println!("Highest score of {score} for:\n\t{can}\n");
-                    first = false;
-                } else {
-                    println!("{can}");
+        ("caesar", Some(sub_matches)) => match sub_matches.subcommand() {
+            ("encrypt", Some(bottom_matches)) => {
+                let input = input::read_text(bottom_matches);
+                let shift = shift(bottom_matches);
+
+                Output::new(bottom_matches).write_result(
+                    caesar_keyed(bottom_matches)
+                        .encrypt(&input, shift)
+                        .as_bytes(),
+                );
+            }
+            ("decrypt", Some(bottom_matches)) => {
+                let input = input::read_text(bottom_matches);
+                let shift = shift(bottom_matches);
+
+                Output::new(bottom_matches).write_result(
+                    caesar_keyed(bottom_matches)
+                        .decrypt(&input, shift)
+                        .as_bytes(),
+                );
+            }
+            ("crack", Some(bottom_matches)) if bottom_matches.is_present("wordlist") => {
+                caesar_wordlist(bottom_matches)
+            }
+            ("crack", Some(bottom_matches)) => {
+                let input = input::read_text(bottom_matches);
+                let mut output = Output::new(bottom_matches);
+
+                // bruteforce string over every shift of the (keyed) alphabet
+                let res = caesar_keyed(bottom_matches).brute_force(&input);
+
+                // order string by most likely string in the chosen language
+                let scorer = caesar_scorer(bottom_matches);
+                let res =
+                    score_strings_with(res, scoring::scorer_or(bottom_matches, scorer).as_ref());
+
+                // raw output is just the best candidate so it can be piped on
+                if output.format() == Format::Raw {
+                    if unranked(res.iter().map(|x| x.1)) {
+                        exit_with_error(unranked_error(scoring::name_or(bottom_matches, scorer)));
+                    }
+                    output.write_result(res[0].0.as_bytes());
+                    return;
+                }
+
+                let english = scoring::lang(bottom_matches).code == "en";
+                report(&mut output, |out| {
+                    writeln!(out, "Brute force results for: {}\n", input)?;
+
+                    if unranked(res.iter().map(|x| x.1)) {
+                        writeln!(
+                            out,
+                            "Every shift scores the same by {}, listed in order:",
+                            scoring::name_or(bottom_matches, scorer)
+                        )?;
+                        for (shift, (can, _)) in res.iter().enumerate() {
+                            writeln!(out, "{shift}\t{}", out.render(can.as_bytes()))?;
+                        }
+                        return Ok(());
+                    }
+
+                    writeln!(
+                        out,
+                        "Ordered by {} language {} score:",
+                        scoring::lang(bottom_matches).name,
+                        scoring::name_or(bottom_matches, scorer)
+                    )?;
+                    let mut first = true;
+                    for (can, score) in &res {
+                        let rendered = out.render(can.as_bytes());
+                        if first {
+                            writeln!(out, "Highest score of {score} for:\n\t{rendered}")?;
+
+                            // spaces stripped from the ciphertext are put back,
+                            // the word list segmenting it is english only
+                            if !can.contains(char::is_whitespace) && english {
+                                let spaced = segment::spaced(can);
+                                if spaced != *can {
+                                    writeln!(out, "\t{}", out.render(spaced.as_bytes()))?;
+                                }
+                            }
+                            writeln!(out)?;
+                            first = false;
+                        } else {
+                            writeln!(out, "{rendered}")?;
+                        }
+                    }
+                    Ok(())
+                });
+            }
+            _ => unreachable!(),
+        },
+
+        // HANDLE AFFINE SUB COMMANDS
+        ("affine", Some(sub_matches)) => match sub_matches.subcommand() {
+            ("encrypt", Some(bottom_matches)) => {
+                let input = input::read_text(bottom_matches);
+
+                Output::new(bottom_matches)
+                    .write_result(affine::encrypt(&input, affine_key(bottom_matches)).as_bytes());
+            }
+            ("decrypt", Some(bottom_matches)) => {
+                let input = input::read_text(bottom_matches);
+
+                Output::new(bottom_matches)
+                    .write_result(affine::decrypt(&input, affine_key(bottom_matches)).as_bytes());
+            }
+            ("recover", Some(bottom_matches)) => {
+                let plain = bottom_matches.value_of("plain").unwrap();
+                let cipher = bottom_matches.value_of("cipher").unwrap();
+                if plain.chars().count() != cipher.chars().count() {
+                    exit_with_error("--plain and --cipher must have as many letters as each other");
+                }
+
+                let pairs = plain
+                    .chars()
+                    .zip(cipher.chars())
+                    .collect::<Vec<(char, char)>>();
+                let keys = affine::recover(&pairs).unwrap_or_else(|e| exit_with_error(e));
+                if keys.is_empty() {
+                    exit_with_error(format!("no affine key encrypts {plain:?} to {cipher:?}"));
+                }
+
+                report(&mut Output::new(bottom_matches), |out| {
+                    for key in keys {
+                        writeln!(out, "{key}\t(a^-1={})", key.inverse())?;
+                    }
+                    Ok(())
+                });
+            }
+            ("crack", Some(bottom_matches)) => {
+                let input = input::read_text(bottom_matches);
+                let mut output = Output::new(bottom_matches);
+
+                // scored with their key, keys giving the same plaintext are all
+                // kept
+                let scorer = scoring::scorer(bottom_matches);
+                let mut res = affine::brute_force(&input)
+                    .into_iter()
+                    .map(|(key, plaintext)| {
+                        let score = scorer.score(&plaintext);
+                        (key, plaintext, score)
+                    })
+                    .collect::<Vec<(affine::Key, String, f64)>>();
+                res.sort_by(|a, b| descending(a.2, b.2));
+
+                // raw output is just the best candidate so it can be piped on
+                if output.format() == Format::Raw {
+                    if unranked(res.iter().map(|x| x.2)) {
+                        exit_with_error(unranked_error(scoring::name(bottom_matches)));
+                    }
+                    output.write_result(res[0].1.as_bytes());
+                    return;
                 }
+
+                report(&mut output, |out| {
+                    writeln!(out, "Brute force results for: {}\n", input)?;
+
+                    // the sort is stable, so tied keys are still in key order
+                    if unranked(res.iter().map(|x| x.2)) {
+                        writeln!(
+                            out,
+                            "Every key scores the same by {}, listed in order:",
+                            scoring::name(bottom_matches)
+                        )?;
+                        for (key, can, _) in &res {
+                            writeln!(out, "{key}\t{}", out.render(can.as_bytes()))?;
+                        }
+                        return Ok(());
+                    }
+
+                    writeln!(
+                        out,
+                        "Ordered by {} language {} score:",
+                        scoring::lang(bottom_matches).name,
+                        scoring::name(bottom_matches)
+                    )?;
+                    for (key, can, score) in res.iter().take(top(bottom_matches)) {
+                        let can = out.render(can.as_bytes());
+                        writeln!(out, "{score:.4}\t{key}\t{can}")?;
+                    }
+                    Ok(())
+                });
             }
+            _ => unreachable!(),
+        },
+
+        // HANDLE ANALYZE SUB COMMAND
+        ("analyze", Some(sub_matches)) => {
+            let input = input::read_bytes(sub_matches);
+
+            report(&mut Output::new(sub_matches), |out| {
+                analyze::report(out, &input, scoring::lang(sub_matches))
+            });
         }
 
         // HANDLE BASE64 SUB COMMANDS
         ("base64", Some(sub_matches)) => match sub_matches.subcommand() {
             ("decode", Some(bottom_matches)) => {
-                let input = bottom_matches.value_of("input").unwrap();
-                let decoded = base64::decode(input.trim());
+                let engine = base64_engine(bottom_matches);
+                let mut output = Output::new(bottom_matches);
 
-                let attempt = String::from_utf8(decoded);
+                match input::literal(bottom_matches) {
+                    Some(input) => {
+                        let input = input.trim();
+                        let decoded = engine
+                            .decode(input)
+                            .unwrap_or_else(|e| report_decode_error(input, e.offset(), e));
 
-                match attempt {
-                    Ok(good) => println!("{good}"),
-                    Err(_) => println!("Error displaying decoded"),
-                };
+                        output.write_result(&decoded);
+                    }
+                    // stream everything else so large inputs run in constant memory
+                    None => output.write_stream(|out| {
+                        let mut reader = base64::DecoderReader::new(
+                            input::TrimEnd::new(input::reader(bottom_matches)),
+                            engine,
+                        );
+                        io::copy(&mut reader, out).map(|_| ())
+                    }),
+                }
             }
             ("encode", Some(bottom_matches)) => {
-                let input = bottom_matches.value_of("input").unwrap();
-                println!("{}", base64::encode(Vec::from(input)))
+                let engine = base64_engine(bottom_matches);
+
+                Output::new(bottom_matches).write_stream(|out| {
+                    let mut writer = base64::EncoderWriter::new(out, engine);
+                    io::copy(&mut input::reader(bottom_matches), &mut writer)?;
+                    writer.finish().map(|_| ())
+                });
             }
             _ => unreachable!(),
         },
@@ -101,23 +631,637 @@ fn main() {
         // handle hex subcommand
         ("hex", Some(sub_matches)) => match sub_matches.subcommand() {
             ("decode", Some(bottom_matches)) => {
-                let input = bottom_matches.value_of("input").unwrap();
-                let decoded = hex::decode(input);
+                let mut output = Output::new(bottom_matches);
+
+                match input::literal(bottom_matches) {
+                    Some(input) => {
+                        let decoded = hex::decode(input)
+                            .unwrap_or_else(|e| report_decode_error(input, e.offset(), e));
 
-                let attempt = String::from_utf8(decoded.clone());
-                match attempt {
-                    Ok(x) => println!("{}", x),
-                    Err(_) => println!("{:?}", decoded),
+                        output.write_result(&decoded);
+                    }
+                    None => output.write_stream(|out| {
+                        let mut reader = hex::DecoderReader::new(input::TrimEnd::new(
+                            input::reader(bottom_matches),
+                        ));
+                        io::copy(&mut reader, out).map(|_| ())
+                    }),
                 }
             }
             ("encode", Some(bottom_matches)) => {
-                let input = bottom_matches.value_of("input").unwrap();
-                println!("{}", hex::encode(Vec::<u8>::from(input)));
+                Output::new(bottom_matches).write_stream(|out| {
+                    let mut writer = hex::EncoderWriter::new(out);
+                    io::copy(&mut input::reader(bottom_matches), &mut writer)?;
+                    writer.finish().map(|_| ())
+                });
             }
             _ => unreachable!(),
         },
 
+        // HANDLE MAGIC SUB COMMAND
+        ("magic", Some(sub_matches)) => {
+            let input = input::read_bytes(sub_matches);
+            let mut output = Output::new(sub_matches);
+
+            let depth = sub_matches.value_of("depth").unwrap().parse().unwrap();
+            let top = top(sub_matches);
+
+            let scorer = scoring::scorer(sub_matches);
+            let candidates = magic::search(&input, depth, scorer.as_ref());
+
+            // raw output is just the best decoding so it can be piped on
+            if output.format() == Format::Raw {
+                match candidates.first() {
+                    Some(best) => output.write_result(&best.output),
+                    None => exit_with_error("no decodings of the input to write"),
+                }
+                return;
+            }
+
+            report(&mut output, |out| {
+                writeln!(
+                    out,
+                    "Best {} of {} decodings, ordered by {} score:\n",
+                    top.min(candidates.len()),
+                    candidates.len(),
+                    scoring::name(sub_matches)
+                )?;
+
+                for candidate in candidates.iter().take(top) {
+                    let recipe = match candidate.recipe.steps.is_empty() {
+                        true => "(input as is)".to_string(),
+                        false => candidate.recipe.to_string(),
+                    };
+                    let preview = preview(&out.render(&candidate.output));
+
+                    writeln!(out, "{:.4}\t{recipe}\n\t{preview}\n", candidate.score)?;
+                }
+                Ok(())
+            });
+        }
+
+        // HANDLE RECIPE SUB COMMAND
+        ("recipe", Some(sub_matches)) => {
+            let recipe = match sub_matches.value_of("load") {
+                Some(path) => Recipe::load(Path::new(path)),
+                None => sub_matches.value_of("recipe").unwrap().parse::<Recipe>(),
+            }
+            .unwrap_or_else(|e| exit_with_error(e));
+
+            if let Some(path) = sub_matches.value_of("save") {
+                return recipe
+                    .save(Path::new(path))
+                    .unwrap_or_else(|e| exit_with_error(e));
+            }
+
+            let input = input::read_bytes(sub_matches);
+            let result = recipe.run(input).unwrap_or_else(|e| exit_with_error(e));
+
+            Output::new(sub_matches).write_result(&result);
+        }
+
+        // HANDLE XOR SUB COMMANDS
+        ("xor", Some(sub_matches)) => {
+            match sub_matches.subcommand() {
+                ("single", Some(bottom_matches)) => {
+                    let input = input::read_bytes(bottom_matches);
+                    let mut output = Output::new(bottom_matches);
+                    let top = top(bottom_matches);
+
+                    let scorer = scoring::scorer(bottom_matches);
+                    let candidates = xor::brute_force_single_byte_with(&input, scorer.as_ref());
+
+                    // raw output is just the best plaintext so it can be piped on
+                    if output.format() == Format::Raw {
+                        output.write_result(&candidates[0].1);
+                        return;
+                    }
+
+                    report(&mut output, |out| {
+                        writeln!(
+                            out,
+                            "Single byte XOR keys for {} bytes, ordered by {} score:\n",
+                            input.len(),
+                            scoring::name(bottom_matches)
+                        )?;
+
+                        let mut first = true;
+                        for (key, plaintext, score) in candidates.iter().take(top) {
+                            let plaintext = preview(&out.render(plaintext));
+                            if first {
+                                writeln!(out, "Highest score of {score:.4} for key {key:#04x}:\n\t{plaintext}\n")?;
+                                first = false;
+                            } else {
+                                writeln!(out, "{score:.4}\t{key:#04x}\t{plaintext}")?;
+                            }
+                        }
+                        Ok(())
+                    });
+                }
+                ("encrypt", Some(bottom_matches)) => {
+                    let key = bottom_matches.value_of("key").unwrap();
+                    let key = match bottom_matches.is_present("hex-key") {
+                        true => hex::decode(key)
+                            .unwrap_or_else(|e| report_decode_error(key, e.offset(), e)),
+                        false => key.as_bytes().to_vec(),
+                    };
+
+                    let input = input::read_bytes(bottom_matches);
+
+                    Output::new(bottom_matches).write_result(&xor::repeating_key(&input, &key));
+                }
+                ("break", Some(bottom_matches)) => {
+                    let input = input::read_bytes(bottom_matches);
+                    let mut output = Output::new(bottom_matches);
+                    let max_size = bottom_matches
+                        .value_of("max-key-size")
+                        .unwrap()
+                        .parse()
+                        .unwrap();
+                    let top = top(bottom_matches);
+
+                    let scorer = scoring::scorer(bottom_matches);
+
+                    let recovered = xor::break_repeating_key_with(&input, max_size, &*scorer)
+                        .unwrap_or_else(|| {
+                            exit_with_error(
+                                "ciphertext is too short to break, it needs at least two bytes",
+                            )
+                        });
+
+                    // raw output is just the plaintext so it can be piped on
+                    if output.format() == Format::Raw {
+                        output.write_result(&recovered.plaintext);
+                        return;
+                    }
+
+                    // every candidate size is solved, the smallest whose
+                    // plaintext scores about as well as the best is chosen
+                    let chosen = recovered.key.len();
+
+                    report(&mut output, |out| {
+                        writeln!(out, "Likeliest key sizes of {} bytes:\n", input.len())?;
+                        writeln!(out, "size\tfit\tdistance\tcoincidence\tsolved")?;
+                        let shown = recovered
+                            .key_sizes
+                            .iter()
+                            .enumerate()
+                            .filter(|(rank, x)| *rank < top || x.size == chosen);
+                        for (rank, size) in shown {
+                            let solved = match size.size {
+                                x if x == chosen => "chosen",
+                                _ if rank < recovered.solved => "yes",
+                                _ => "",
+                            };
+                            writeln!(
+                                out,
+                                "{}\t{:.2}\t{:.4}\t\t{:.4}\t\t{solved}",
+                                size.size,
+                                size.fit(),
+                                size.distance,
+                                size.coincidence
+                            )?;
+                        }
+
+                        writeln!(
+                            out,
+                            "\nRecovered {} byte key with {:.1}% confidence by {} score:\n\t{}\n\t{}\n",
+                            recovered.key.len(),
+                            recovered.confidence * 100.0,
+                            scoring::name(bottom_matches),
+                            preview(&String::from_utf8_lossy(&recovered.key)),
+                            hex::encode(recovered.key.clone())
+                        )?;
+
+                        let plaintext = out.render(&recovered.plaintext);
+                        writeln!(out, "Plaintext:\n{plaintext}")
+                    });
+                }
+                ("detect", Some(bottom_matches)) => {
+                    let contents = input::read_text(bottom_matches);
+                    let mut output = Output::new(bottom_matches);
+                    let top = top(bottom_matches);
+
+                    // blank lines are skipped but still counted for line numbers,
+                    // one bad line among hundreds shouldn't stop the search
+                    let (line_numbers, candidates): (Vec<usize>, Vec<Vec<u8>>) = contents
+                        .lines()
+                        .enumerate()
+                        .filter(|(_, line)| !line.trim().is_empty())
+                        .filter_map(|(number, line)| {
+                            let decoded = match bottom_matches.is_present("b64") {
+                                true => base64::decode(line.trim()).map_err(|e| e.to_string()),
+                                false => hex::decode(line.trim()).map_err(|e| e.to_string()),
+                            };
+
+                            match decoded {
+                                Ok(bytes) => Some((number + 1, bytes)),
+                                Err(e) => {
+                                    eprintln!("warning: skipping line {}: {e}", number + 1);
+                                    None
+                                }
+                            }
+                        })
+                        .unzip();
+
+                    let scorer = scoring::scorer(bottom_matches);
+                    let detections = xor::detect_single_byte_with(&candidates, scorer.as_ref());
+                    if detections.is_empty() {
+                        exit_with_error("no lines to check");
+                    }
+
+                    // raw output is just the best plaintext so it can be piped on
+                    if output.format() == Format::Raw {
+                        output.write_result(&detections[0].plaintext);
+                        return;
+                    }
+
+                    report(&mut output, |out| {
+                        writeln!(
+                            out,
+                            "Best single byte XOR of {} lines, ordered by {} score:\n",
+                            detections.len(),
+                            scoring::name(bottom_matches)
+                        )?;
+
+                        let best = &detections[0];
+                        writeln!(
+                            out,
+                            "Highest score of {:.4} on line {} with key {:#04x}:\n\t{}\n",
+                            best.score,
+                            line_numbers[best.index],
+                            best.key,
+                            preview(&out.render(&best.plaintext))
+                        )?;
+
+                        for detection in detections.iter().take(top).skip(1) {
+                            writeln!(
+                                out,
+                                "{:.4}\tline {}\t{:#04x}\t{}",
+                                detection.score,
+                                line_numbers[detection.index],
+                                detection.key,
+                                preview(&out.render(&detection.plaintext))
+                            )?;
+                        }
+                        Ok(())
+                    });
+                }
+                ("crib", Some(bottom_matches)) => {
+                    // the prompt reads its commands from stdin
+                    let interactive = bottom_matches.is_present("interactive");
+                    if interactive
+                        && bottom_matches.value_of("file").is_none()
+                        && matches!(bottom_matches.value_of("input"), None | Some("-"))
+                    {
+                        exit_with_error(
+                            "--interactive reads commands from stdin, give the ciphertexts as arguments or --file",
+                        );
+                    }
+
+                    let literal = bottom_matches.is_present("input")
+                        && bottom_matches.value_of("input") != Some("-");
+                    let ciphertexts = input::read_lines(bottom_matches)
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, line)| !line.trim().is_empty())
+                        .map(|(number, line)| {
+                            let line = line.trim();
+                            input::decode_line(bottom_matches, line).unwrap_or_else(
+                                |(offset, e)| match literal {
+                                    true => report_decode_error(line, offset, e),
+                                    false => exit_with_error(format!("line {}: {e}", number + 1)),
+                                },
+                            )
+                        })
+                        .collect::<Vec<Vec<u8>>>();
+
+                    if ciphertexts.len() < 2 {
+                        exit_with_error("crib dragging needs at least two ciphertexts");
+                    }
+
+                    let top = top(bottom_matches);
+
+                    if interactive {
+                        let mut workbench = xor::Workbench::new(ciphertexts);
+                        if let Err(e) = crib::interactive(
+                            &mut workbench,
+                            &mut io::stdin().lock(),
+                            &mut io::stdout(),
+                            top,
+                        ) {
+                            exit_with_error(e);
+                        }
+                        return;
+                    }
+
+                    let crib = bottom_matches.value_of("crib").unwrap();
+                    report(&mut Output::new(bottom_matches), |out| {
+                        crib::report(out, &ciphertexts, crib.as_bytes(), top)
+                    });
+                }
+                _ => unreachable!(),
+            }
+        }
+
+        // HANDLE MODEL SUB COMMANDS
+        ("model", Some(sub_matches)) => {
+            if let ("train", Some(train_matches)) = sub_matches.subcommand() {
+                let corpus = train_matches.value_of("corpus").unwrap();
+                let order = train_matches.value_of("order").unwrap().parse().unwrap();
+                let out = Path::new(train_matches.value_of("out").unwrap());
+
+                let corpus =
+                    fs::read(corpus).unwrap_or_else(|e| exit_with_error(format!("{corpus}: {e}")));
+                let model = NgramModel::train(&String::from_utf8_lossy(&corpus), order)
+                    .and_then(|model| model.save(out).map(|_| model))
+                    .unwrap_or_else(|e| exit_with_error(e));
+
+                println!(
+                    "Trained order {} model on {} letters, {} distinct n-grams, saved to {}",
+                    model.order(),
+                    model.sample_size(),
+                    model.distinct(),
+                    out.display()
+                );
+            }
+        }
+
         // should never be reached due to use of CLAP.
         _ => unreachable!(),
     }
 }
+
+// Write a text report, exiting if the output can't be written.
+fn report(output: &mut Output, f: impl FnOnce(&mut Output) -> io::Result<()>) {
+    if let Err(e) = f(output).and_then(|_| output.flush()) {
+        exit_with_error(e);
+    }
+}
+
+fn top_arg(help: &'static str) -> Arg<'static, 'static> {
+    Arg::with_name("top")
+        .long("top")
+        .takes_value(true)
+        .default_value("5")
+        .validator(|x| match x.parse::<usize>() {
+            Ok(_) => Ok(()),
+            Err(_) => Err("top must be a number of results".to_string()),
+        })
+        .help(help)
+}
+
+fn top(matches: &ArgMatches) -> usize {
+    matches.value_of("top").unwrap().parse().unwrap()
+}
+
+// First characters of a rendered result on one line, so rankings stay
+// readable whatever the data holds.
+fn preview(rendered: &str) -> String {
+    let mut preview = rendered
+        .chars()
+        .take(PREVIEW)
+        .map(|x| match x {
+            x if x.is_whitespace() => ' ',
+            x if x.is_control() => '.',
+            x => x,
+        })
+        .collect::<String>();
+    if rendered.chars().count() > PREVIEW {
+        preview.push_str("...");
+    }
+
+    preview
+}
+
+// --alphabet and --keyword for every caesar mode.
+fn caesar_args() -> Vec<Arg<'static, 'static>> {
+    vec![
+        Arg::with_name("alphabet")
+            .long("alphabet")
+            .takes_value(true)
+            .default_value("latin")
+            .help("latin (or letters), cyrillic, greek, digits, alphanumeric, printable (which covers ROT47) or custom letters in order"),
+        Arg::with_name("keyword")
+            .long("keyword")
+            .takes_value(true)
+            .help("Keyed caesar, the shift lands on the alphabet mixed to start with this keyword"),
+    ]
+}
+
+fn shift_arg() -> Arg<'static, 'static> {
+    Arg::with_name("shift")
+        .long("shift")
+        .takes_value(true)
+        .required(true)
+        .validator(|x| match x.parse::<usize>() {
+            Ok(_) => Ok(()),
+            Err(_) => Err("shift must be a number of places".to_string()),
+        })
+        .help("Places to shift each character along the alphabet")
+}
+
+fn shift(matches: &ArgMatches) -> usize {
+    matches.value_of("shift").unwrap().parse().unwrap()
+}
+
+fn caesar_alphabet(matches: &ArgMatches) -> Alphabet {
+    matches
+        .value_of("alphabet")
+        .unwrap()
+        .parse()
+        .unwrap_or_else(|e| exit_with_error(e))
+}
+
+// Default scorer for the alphabet. Rotating more than letters changes
+// punctuation and digits that only the bytes scorer looks at.
+fn caesar_scorer(matches: &ArgMatches) -> &'static str {
+    match matches.value_of("alphabet") {
+        Some("digits" | "alphanumeric" | "printable") => "bytes",
+        _ => "frequency",
+    }
+}
+
+// Candidates that all score the same can't be ranked, e.g. cyrillic or greek
+// rotations, whose letters the english scorers don't see.
+fn unranked(scores: impl IntoIterator<Item = f64>) -> bool {
+    let scores = scores.into_iter().collect::<Vec<f64>>();
+    scores.len() > 1 && scores.windows(2).all(|x| x[0] == x[1])
+}
+
+fn unranked_error(scorer: &str) -> String {
+    format!("every candidate scores the same by {scorer}, there's no best one to write")
+}
+
+fn caesar_keyed(matches: &ArgMatches) -> Keyed {
+    Keyed::new(
+        &caesar_alphabet(matches),
+        matches.value_of("keyword").unwrap_or_default(),
+    )
+    .unwrap_or_else(|e| exit_with_error(e))
+}
+
+// Keyed caesar brute force over every keyword in --wordlist and every shift.
+fn caesar_wordlist(matches: &ArgMatches) {
+    let path = matches.value_of("wordlist").unwrap();
+    let words =
+        fs::read_to_string(path).unwrap_or_else(|e| exit_with_error(format!("{path}: {e}")));
+
+    let input = input::read_text(matches);
+    let mut output = Output::new(matches);
+
+    let candidates = brute_force_keywords(
+        &caesar_alphabet(matches),
+        &input,
+        words.lines().map(str::trim).filter(|x| !x.is_empty()),
+    );
+    if candidates.is_empty() {
+        exit_with_error(format!(
+            "{path} has no keywords with any of the alphabet's symbols"
+        ));
+    }
+
+    // scored with their keyword and shift, keywords giving the same plaintext
+    // are all kept
+    let scorer = caesar_scorer(matches);
+    let scoring = scoring::scorer_or(matches, scorer);
+    let mut res = candidates
+        .iter()
+        .map(|x| (x, scoring.score(&x.plaintext)))
+        .collect::<Vec<(&KeyedCandidate, f64)>>();
+    res.sort_by(|a, b| descending(a.1, b.1));
+
+    // raw output is just the best candidate so it can be piped on
+    if output.format() == Format::Raw {
+        if unranked(res.iter().map(|x| x.1)) {
+            exit_with_error(unranked_error(scoring::name_or(matches, scorer)));
+        }
+        output.write_result(res[0].0.plaintext.as_bytes());
+        return;
+    }
+
+    report(&mut output, |out| {
+        writeln!(
+            out,
+            "Keyed brute force of {} keyword and shift pairs from {path}\n",
+            candidates.len()
+        )?;
+        writeln!(
+            out,
+            "Ordered by {} language {} score:",
+            scoring::lang(matches).name,
+            scoring::name_or(matches, scorer)
+        )?;
+        for (candidate, score) in res.iter().take(top(matches)) {
+            let can = out.render(candidate.plaintext.as_bytes());
+            writeln!(
+                out,
+                "{score:.4}\t{}\t{}\t{can}",
+                candidate.keyword, candidate.shift
+            )?;
+        }
+        Ok(())
+    });
+}
+
+fn affine_key_args() -> Vec<Arg<'static, 'static>> {
+    let number = |x: String| match x.parse::<u32>() {
+        Ok(_) => Ok(()),
+        Err(_) => Err("affine keys are whole numbers".to_string()),
+    };
+
+    vec![
+        Arg::with_name("a")
+            .short("a")
+            .takes_value(true)
+            .required(true)
+            .validator(number)
+            .help("Multiplier, odd and not 13 so it has an inverse mod 26"),
+        Arg::with_name("b")
+            .short("b")
+            .takes_value(true)
+            .required(true)
+            .validator(number)
+            .help("Shift"),
+    ]
+}
+
+fn affine_key(matches: &ArgMatches) -> affine::Key {
+    let a = matches.value_of("a").unwrap().parse().unwrap();
+    let b = matches.value_of("b").unwrap().parse().unwrap();
+
+    affine::Key::new(a, b).unwrap_or_else(|e| exit_with_error(e))
+}
+
+fn base64_alphabet_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("alphabet")
+        .long("alphabet")
+        .takes_value(true)
+        .default_value("standard")
+        .help("standard, url, imap, bcrypt, crypt or a custom 64 character alphabet")
+}
+
+// Build the base64 engine described by the encode/decode flags.
+fn base64_engine(matches: &ArgMatches) -> base64::Engine {
+    let alphabet = matches
+        .value_of("alphabet")
+        .unwrap()
+        .parse::<base64::Alphabet>()
+        .unwrap_or_else(|e| exit_with_error(e));
+
+    let padding = match matches.value_of("padding") {
+        _ if matches.is_present("no-pad") => base64::PaddingMode::Forbidden,
+        Some("optional") => base64::PaddingMode::Optional,
+        Some("forbidden") => base64::PaddingMode::Forbidden,
+        _ => base64::PaddingMode::Required,
+    };
+
+    let mut config = base64::Config::new()
+        .with_padding(padding)
+        .with_strict(matches.is_present("strict"))
+        .with_lenient(matches.is_present("lenient"));
+
+    if let Some(width) = matches.value_of("wrap") {
+        let ending = match matches.value_of("line-ending") {
+            Some("crlf") => base64::LineEnding::CrLf,
+            _ => base64::LineEnding::Lf,
+        };
+
+        config = config.with_line_wrap(width.parse().unwrap(), ending);
+    }
+
+    base64::Engine::new(alphabet, config)
+}
+
+pub(crate) fn exit_with_error(error: impl Display) -> ! {
+    eprintln!("error: {error}");
+    process::exit(1);
+}
+
+// Print a decode error with the offending line of the input and a caret under
+// the bad position, then exit.
+pub(crate) fn report_decode_error(input: &str, offset: usize, error: impl Display) -> ! {
+    let line_start = input[..offset].rfind('\n').map_or(0, |x| x + 1);
+    let line_end = input[offset..]
+        .find('\n')
+        .map_or(input.len(), |x| offset + x);
+    let line = &input[line_start..line_end];
+
+    let position = line[..offset - line_start].chars().count();
+    let start = position.saturating_sub(DIAGNOSTIC_CONTEXT);
+
+    // tabs and carriage returns would throw the caret out of line
+    let window = line
+        .chars()
+        .skip(start)
+        .take(DIAGNOSTIC_CONTEXT * 2 + 1)
+        .map(|x| if x.is_whitespace() { ' ' } else { x })
+        .collect::<String>();
+    let prefix = if start > 0 { "..." } else { "" };
+
+    eprintln!("error: {error}");
+    eprintln!("    {prefix}{window}");
+    eprintln!("    {}^", " ".repeat(prefix.len() + position - start));
+
+    process::exit(1);
+}
@@ -0,0 +1,309 @@
+// Operations are the steps of a recipe. Each one takes the bytes produced by
+// the step before it and hands new bytes on to the next.
+use base64::base64;
+use caesar::caesar::rotate_string;
+use hex::hex;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use xor::xor;
+
+pub trait Operation {
+    // Name the operation is written as in a recipe.
+    fn name(&self) -> &'static str;
+
+    // Parameters as written after the name in a recipe.
+    fn arguments(&self) -> Vec<String>;
+
+    // Build the operation from the parameters written after its name.
+    fn from_arguments(arguments: &[&str]) -> Result<Self, String>
+    where
+        Self: Sized;
+
+    fn run(&self, input: &[u8]) -> Result<Vec<u8>, OperationError>;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OperationError {
+    pub operation: &'static str,
+    pub message: String,
+}
+
+impl fmt::Display for OperationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.operation, self.message)
+    }
+}
+
+impl std::error::Error for OperationError {}
+
+// Every operation a recipe can use, tagged by name when saved to a file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum Step {
+    FromBase64(FromBase64),
+    ToBase64(ToBase64),
+    FromHex(FromHex),
+    ToHex(ToHex),
+    Rot(Rot),
+    Xor(Xor),
+}
+
+pub const OPERATION_NAMES: [&str; 6] = [
+    "from_base64",
+    "to_base64",
+    "from_hex",
+    "to_hex",
+    "rot",
+    "xor",
+];
+
+impl Step {
+    // None if there is no operation with the name, otherwise whether the
+    // arguments suit it.
+    pub fn parse(name: &str, arguments: &[&str]) -> Option<Result<Step, String>> {
+        Some(match name {
+            "from_base64" => FromBase64::from_arguments(arguments).map(Step::FromBase64),
+            "to_base64" => ToBase64::from_arguments(arguments).map(Step::ToBase64),
+            "from_hex" => FromHex::from_arguments(arguments).map(Step::FromHex),
+            "to_hex" => ToHex::from_arguments(arguments).map(Step::ToHex),
+            "rot" => Rot::from_arguments(arguments).map(Step::Rot),
+            "xor" => Xor::from_arguments(arguments).map(Step::Xor),
+            _ => return None,
+        })
+    }
+
+    pub fn operation(&self) -> &dyn Operation {
+        match self {
+            Step::FromBase64(x) => x,
+            Step::ToBase64(x) => x,
+            Step::FromHex(x) => x,
+            Step::ToHex(x) => x,
+            Step::Rot(x) => x,
+            Step::Xor(x) => x,
+        }
+    }
+}
+
+fn no_arguments(name: &str, arguments: &[&str]) -> Result<(), String> {
+    match arguments.is_empty() {
+        true => Ok(()),
+        false => Err(format!("{name} doesn't take any arguments")),
+    }
+}
+
+fn default_alphabet() -> String {
+    "standard".to_string()
+}
+
+// Alphabet argument shared by the base64 operations, checked up front so a bad
+// recipe fails before it runs.
+fn alphabet_argument(name: &str, arguments: &[&str]) -> Result<String, String> {
+    match arguments {
+        [] => Ok(default_alphabet()),
+        [alphabet] => alphabet
+            .parse::<base64::Alphabet>()
+            .map(|_| alphabet.to_string())
+            .map_err(|e| e.to_string()),
+        _ => Err(format!("{name} takes at most an alphabet")),
+    }
+}
+
+fn alphabet_arguments(alphabet: &str) -> Vec<String> {
+    match alphabet {
+        "standard" => vec![],
+        alphabet => vec![alphabet.to_string()],
+    }
+}
+
+// Forgiving about padding and line breaks, like pasting into a decoder by hand.
+fn base64_engine(
+    alphabet: &str,
+    operation: &'static str,
+) -> Result<base64::Engine, OperationError> {
+    let alphabet = alphabet
+        .parse::<base64::Alphabet>()
+        .map_err(|e| OperationError {
+            operation,
+            message: e.to_string(),
+        })?;
+
+    let config = base64::Config::new()
+        .with_padding(base64::PaddingMode::Optional)
+        .with_lenient(true);
+
+    Ok(base64::Engine::new(alphabet, config))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FromBase64 {
+    #[serde(default = "default_alphabet")]
+    pub alphabet: String,
+}
+
+impl Operation for FromBase64 {
+    fn name(&self) -> &'static str {
+        "from_base64"
+    }
+
+    fn arguments(&self) -> Vec<String> {
+        alphabet_arguments(&self.alphabet)
+    }
+
+    fn from_arguments(arguments: &[&str]) -> Result<FromBase64, String> {
+        Ok(FromBase64 {
+            alphabet: alphabet_argument("from_base64", arguments)?,
+        })
+    }
+
+    fn run(&self, input: &[u8]) -> Result<Vec<u8>, OperationError> {
+        let engine = base64_engine(&self.alphabet, self.name())?;
+
+        engine
+            .decode(&String::from_utf8_lossy(input))
+            .map_err(|e| OperationError {
+                operation: self.name(),
+                message: e.to_string(),
+            })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToBase64 {
+    #[serde(default = "default_alphabet")]
+    pub alphabet: String,
+}
+
+impl Operation for ToBase64 {
+    fn name(&self) -> &'static str {
+        "to_base64"
+    }
+
+    fn arguments(&self) -> Vec<String> {
+        alphabet_arguments(&self.alphabet)
+    }
+
+    fn from_arguments(arguments: &[&str]) -> Result<ToBase64, String> {
+        Ok(ToBase64 {
+            alphabet: alphabet_argument("to_base64", arguments)?,
+        })
+    }
+
+    fn run(&self, input: &[u8]) -> Result<Vec<u8>, OperationError> {
+        let engine = base64_engine(&self.alphabet, self.name())?;
+
+        Ok(engine.encode(input).into_bytes())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FromHex;
+
+impl Operation for FromHex {
+    fn name(&self) -> &'static str {
+        "from_hex"
+    }
+
+    fn arguments(&self) -> Vec<String> {
+        vec![]
+    }
+
+    fn from_arguments(arguments: &[&str]) -> Result<FromHex, String> {
+        no_arguments("from_hex", arguments).map(|_| FromHex)
+    }
+
+    fn run(&self, input: &[u8]) -> Result<Vec<u8>, OperationError> {
+        hex::decode(&String::from_utf8_lossy(input)).map_err(|e| OperationError {
+            operation: self.name(),
+            message: e.to_string(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToHex;
+
+impl Operation for ToHex {
+    fn name(&self) -> &'static str {
+        "to_hex"
+    }
+
+    fn arguments(&self) -> Vec<String> {
+        vec![]
+    }
+
+    fn from_arguments(arguments: &[&str]) -> Result<ToHex, String> {
+        no_arguments("to_hex", arguments).map(|_| ToHex)
+    }
+
+    fn run(&self, input: &[u8]) -> Result<Vec<u8>, OperationError> {
+        Ok(hex::encode(input.to_vec()).into_bytes())
+    }
+}
+
+// Caesar rotation of the letters, negative shifts rotate backwards.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Rot {
+    pub shift: i32,
+}
+
+impl Operation for Rot {
+    fn name(&self) -> &'static str {
+        "rot"
+    }
+
+    fn arguments(&self) -> Vec<String> {
+        vec![self.shift.to_string()]
+    }
+
+    fn from_arguments(arguments: &[&str]) -> Result<Rot, String> {
+        match arguments {
+            [shift] => shift
+                .parse()
+                .map(|shift| Rot { shift })
+                .map_err(|_| format!("rot shift must be a number, not {shift:?}")),
+            _ => Err("rot takes a shift, e.g. rot 13".to_string()),
+        }
+    }
+
+    fn run(&self, input: &[u8]) -> Result<Vec<u8>, OperationError> {
+        let text = String::from_utf8(input.to_vec()).map_err(|_| OperationError {
+            operation: self.name(),
+            message: "input isn't valid UTF-8 text".to_string(),
+        })?;
+
+        Ok(rotate_string(text, self.shift.rem_euclid(26) as u8).into_bytes())
+    }
+}
+
+// XOR every byte with a single key byte, written in hex (0x41) or decimal.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Xor {
+    pub key: u8,
+}
+
+impl Operation for Xor {
+    fn name(&self) -> &'static str {
+        "xor"
+    }
+
+    fn arguments(&self) -> Vec<String> {
+        vec![format!("{:#04x}", self.key)]
+    }
+
+    fn from_arguments(arguments: &[&str]) -> Result<Xor, String> {
+        let key = match arguments {
+            [key] => match key.strip_prefix("0x") {
+                Some(digits) => u8::from_str_radix(digits, 16),
+                None => key.parse(),
+            }
+            .map_err(|_| format!("xor key must be a byte, not {key:?}"))?,
+            _ => return Err("xor takes a key byte, e.g. xor 0x41".to_string()),
+        };
+
+        Ok(Xor { key })
+    }
+
+    fn run(&self, input: &[u8]) -> Result<Vec<u8>, OperationError> {
+        Ok(xor::single_byte(input, self.key))
+    }
+}
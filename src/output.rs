@@ -0,0 +1,172 @@
+// Shared output handling for every subcommand. Results go to stdout or a file,
+// rendered as text, hex, base64, a debug byte list or left as raw bytes.
+use base64::base64;
+use clap::{Arg, ArgMatches};
+use hex::hex;
+use std::fs::File;
+use std::io::{self, BufWriter, IsTerminal, Write};
+
+use crate::exit_with_error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    // Text on a terminal, falling back to debug for binary. Raw bytes anywhere
+    // else so redirected output is exact.
+    Auto,
+    Text,
+    Hex,
+    Base64,
+    Debug,
+    Raw,
+}
+
+pub fn args() -> Vec<Arg<'static, 'static>> {
+    vec![
+        Arg::with_name("out")
+            .long("out")
+            .takes_value(true)
+            .value_name("PATH")
+            .help("Write output to a file instead of stdout"),
+        Arg::with_name("format")
+            .long("format")
+            .takes_value(true)
+            .possible_values(&["auto", "text", "hex", "b64", "debug", "raw"])
+            .default_value("auto")
+            .help("How to render output, auto is text on a terminal and raw bytes otherwise"),
+        Arg::with_name("raw")
+            .long("raw")
+            .conflicts_with("format")
+            .help("Write the exact output bytes, same as --format raw"),
+    ]
+}
+
+pub struct Output {
+    destination: Box<dyn Write>,
+    format: Format,
+    terminal: bool,
+}
+
+impl Output {
+    pub fn new(matches: &ArgMatches) -> Output {
+        let format = match matches.value_of("format") {
+            _ if matches.is_present("raw") => Format::Raw,
+            Some("text") => Format::Text,
+            Some("hex") => Format::Hex,
+            Some("b64") => Format::Base64,
+            Some("debug") => Format::Debug,
+            Some("raw") => Format::Raw,
+            _ => Format::Auto,
+        };
+
+        let (destination, terminal): (Box<dyn Write>, bool) = match matches.value_of("out") {
+            Some(path) => match File::create(path) {
+                Ok(file) => (Box::new(BufWriter::new(file)), false),
+                Err(e) => exit_with_error(format!("couldn't create {path}: {e}")),
+            },
+            None => (
+                Box::new(BufWriter::new(io::stdout())),
+                io::stdout().is_terminal(),
+            ),
+        };
+
+        Output {
+            destination,
+            format,
+            terminal,
+        }
+    }
+
+    pub fn format(&self) -> Format {
+        self.format
+    }
+
+    // Render bytes for showing inside a report, where raw bytes would be
+    // mixed in with text anyway so they're shown as text.
+    pub fn render(&self, bytes: &[u8]) -> String {
+        match self.format {
+            Format::Hex => hex::encode(bytes.to_vec()),
+            Format::Base64 => base64::encode(bytes.to_vec()),
+            Format::Debug => format!("{bytes:?}"),
+            Format::Auto | Format::Text | Format::Raw => {
+                String::from_utf8_lossy(bytes).into_owned()
+            }
+        }
+    }
+
+    // Write a complete result in the chosen format.
+    pub fn write_result(&mut self, bytes: &[u8]) {
+        let result = match self.resolved_format(bytes) {
+            Format::Raw => self.destination.write_all(bytes),
+            format => {
+                let rendered = match format {
+                    Format::Debug => format!("{bytes:?}"),
+                    Format::Text => String::from_utf8_lossy(bytes).into_owned(),
+                    _ => self.render(bytes),
+                };
+                writeln!(self.destination, "{rendered}")
+            }
+        };
+
+        self.finish(result);
+    }
+
+    // Stream a result through the chosen format, f writes the result bytes to
+    // the writer it's given. Formats that have to see the whole result first
+    // are buffered.
+    pub fn write_stream(&mut self, f: impl FnOnce(&mut dyn Write) -> io::Result<()>) {
+        let result = match self.format {
+            Format::Raw => f(&mut self.destination),
+            Format::Auto if !self.terminal => f(&mut self.destination),
+            Format::Hex => {
+                let mut writer = hex::EncoderWriter::new(&mut self.destination);
+                f(&mut writer)
+                    .and_then(|_| writer.finish())
+                    .and_then(|inner| writeln!(inner))
+            }
+            Format::Base64 => {
+                let mut writer =
+                    base64::EncoderWriter::new(&mut self.destination, base64::STANDARD);
+                f(&mut writer)
+                    .and_then(|_| writer.finish())
+                    .and_then(|inner| writeln!(inner))
+            }
+            _ => {
+                let mut buffer = Vec::new();
+                if let Err(e) = f(&mut buffer) {
+                    exit_with_error(e);
+                }
+                return self.write_result(&buffer);
+            }
+        };
+
+        self.finish(result);
+    }
+
+    // Auto picks text when it can be shown, debug when it can't and raw bytes
+    // when not writing to a terminal.
+    fn resolved_format(&self, bytes: &[u8]) -> Format {
+        match self.format {
+            Format::Auto if !self.terminal => Format::Raw,
+            Format::Auto if std::str::from_utf8(bytes).is_ok() => Format::Text,
+            Format::Auto => Format::Debug,
+            format => format,
+        }
+    }
+
+    fn finish(&mut self, result: io::Result<()>) {
+        if let Err(e) = result.and_then(|_| self.destination.flush()) {
+            exit_with_error(e);
+        }
+    }
+}
+
+// Lets reports be written with writeln! alongside formatted results.
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.destination.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.destination.flush()
+    }
+}
@@ -0,0 +1,286 @@
+// A recipe chains operations together, e.g. "from_base64 | from_hex | rot 13".
+// Recipes can be written inline or saved to JSON or TOML files to share.
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::operation::{OperationError, Step, OPERATION_NAMES};
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct Recipe {
+    pub steps: Vec<Step>,
+}
+
+#[derive(Debug)]
+pub enum RecipeError {
+    Empty,
+    UnknownOperation(String),
+    InvalidArguments { operation: String, message: String },
+    // Step failed while running, index counts from 1.
+    Step { index: usize, error: OperationError },
+    File { path: String, message: String },
+}
+
+impl fmt::Display for RecipeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RecipeError::Empty => write!(f, "recipe has no operations"),
+            RecipeError::UnknownOperation(name) => write!(
+                f,
+                "unknown operation {name:?}, expected one of {}",
+                OPERATION_NAMES.join(", ")
+            ),
+            RecipeError::InvalidArguments { operation, message } => {
+                write!(f, "bad arguments for {operation}: {message}")
+            }
+            RecipeError::Step { index, error } => write!(f, "step {index} failed, {error}"),
+            RecipeError::File { path, message } => write!(f, "{path}: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for RecipeError {}
+
+// Files are stored as JSON or TOML going by their extension, anything else
+// holds the inline form.
+enum FileFormat {
+    Json,
+    Toml,
+    Text,
+}
+
+impl FileFormat {
+    fn of(path: &Path) -> FileFormat {
+        match path.extension().and_then(|x| x.to_str()) {
+            Some("json") => FileFormat::Json,
+            Some("toml") => FileFormat::Toml,
+            _ => FileFormat::Text,
+        }
+    }
+}
+
+impl Recipe {
+    pub fn load(path: &Path) -> Result<Recipe, RecipeError> {
+        let file_error = |message: String| RecipeError::File {
+            path: path.display().to_string(),
+            message,
+        };
+
+        let contents = fs::read_to_string(path).map_err(|e| file_error(e.to_string()))?;
+
+        let recipe = match FileFormat::of(path) {
+            FileFormat::Json => {
+                serde_json::from_str::<Recipe>(&contents).map_err(|e| file_error(e.to_string()))?
+            }
+            FileFormat::Toml => {
+                toml::from_str::<Recipe>(&contents).map_err(|e| file_error(e.to_string()))?
+            }
+            FileFormat::Text => contents.parse()?,
+        };
+
+        // files skip the inline parser so check their arguments the same way
+        recipe.validate()?;
+        Ok(recipe)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), RecipeError> {
+        let file_error = |message: String| RecipeError::File {
+            path: path.display().to_string(),
+            message,
+        };
+
+        let contents = match FileFormat::of(path) {
+            FileFormat::Json => {
+                serde_json::to_string_pretty(self).map_err(|e| file_error(e.to_string()))?
+            }
+            FileFormat::Toml => {
+                toml::to_string_pretty(self).map_err(|e| file_error(e.to_string()))?
+            }
+            FileFormat::Text => self.to_string(),
+        };
+
+        fs::write(path, contents + "\n").map_err(|e| file_error(e.to_string()))
+    }
+
+    // Feed the input through every step in turn.
+    pub fn run(&self, input: Vec<u8>) -> Result<Vec<u8>, RecipeError> {
+        self.steps
+            .iter()
+            .enumerate()
+            .try_fold(input, |data, (index, step)| {
+                step.operation()
+                    .run(&data)
+                    .map_err(|error| RecipeError::Step {
+                        index: index + 1,
+                        error,
+                    })
+            })
+    }
+
+    // Round trip every step through its inline form to catch bad parameters.
+    fn validate(&self) -> Result<(), RecipeError> {
+        if self.steps.is_empty() {
+            return Err(RecipeError::Empty);
+        }
+
+        for step in &self.steps {
+            parse_step(&format_step(step))?;
+        }
+
+        Ok(())
+    }
+}
+
+fn parse_step(text: &str) -> Result<Step, RecipeError> {
+    let mut words = text.split_whitespace();
+    let name = words.next().ok_or(RecipeError::Empty)?;
+    let arguments = words.collect::<Vec<&str>>();
+
+    match Step::parse(name, &arguments) {
+        Some(step) => step.map_err(|message| RecipeError::InvalidArguments {
+            operation: name.to_string(),
+            message,
+        }),
+        None => Err(RecipeError::UnknownOperation(name.to_string())),
+    }
+}
+
+fn format_step(step: &Step) -> String {
+    let operation = step.operation();
+
+    std::iter::once(operation.name().to_string())
+        .chain(operation.arguments())
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+impl FromStr for Recipe {
+    type Err = RecipeError;
+
+    fn from_str(text: &str) -> Result<Recipe, RecipeError> {
+        let steps = text
+            .split(['|', '\n'])
+            .filter(|x| !x.trim().is_empty())
+            .map(parse_step)
+            .collect::<Result<Vec<Step>, RecipeError>>()?;
+
+        if steps.is_empty() {
+            return Err(RecipeError::Empty);
+        }
+
+        Ok(Recipe { steps })
+    }
+}
+
+impl fmt::Display for Recipe {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let steps = self.steps.iter().map(format_step).collect::<Vec<String>>();
+
+        write!(f, "{}", steps.join(" | "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::operation::{FromBase64, FromHex, Rot, Step};
+    use crate::recipe::{Recipe, RecipeError};
+
+    #[test]
+    fn test_parse_recipe() {
+        let recipe = "from_base64 | from_hex | rot 13".parse::<Recipe>().unwrap();
+
+        assert_eq!(
+            vec![
+                Step::FromBase64(FromBase64 {
+                    alphabet: "standard".to_string()
+                }),
+                Step::FromHex(FromHex),
+                Step::Rot(Rot { shift: 13 }),
+            ],
+            recipe.steps
+        );
+        assert_eq!("from_base64 | from_hex | rot 13", recipe.to_string());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(matches!("".parse::<Recipe>(), Err(RecipeError::Empty)));
+        assert!(matches!(
+            "from_hex | unrot".parse::<Recipe>(),
+            Err(RecipeError::UnknownOperation(name)) if name == "unrot"
+        ));
+        assert!(matches!(
+            "rot thirteen".parse::<Recipe>(),
+            Err(RecipeError::InvalidArguments { .. })
+        ));
+        assert!(matches!(
+            "to_base64 nope".parse::<Recipe>(),
+            Err(RecipeError::InvalidArguments { .. })
+        ));
+    }
+
+    #[test]
+    fn test_run_recipe() {
+        // "Uryyb" hex encoded then base64 encoded
+        let recipe = "from_base64 | from_hex | rot 13".parse::<Recipe>().unwrap();
+
+        assert_eq!(
+            b"Hello".to_vec(),
+            recipe.run(b"NTU3Mjc5Nzk2Mg==".to_vec()).unwrap()
+        );
+
+        let recipe = "to_hex | to_base64 url | from_base64 url | from_hex | rot -3"
+            .parse::<Recipe>()
+            .unwrap();
+        assert_eq!(b"Ebiil".to_vec(), recipe.run(b"Hello".to_vec()).unwrap());
+    }
+
+    #[test]
+    fn test_run_reports_failed_step() {
+        let recipe = "from_hex | from_hex".parse::<Recipe>().unwrap();
+
+        match recipe.run(b"7a7a".to_vec()) {
+            Err(RecipeError::Step { index, error }) => {
+                assert_eq!(2, index);
+                assert_eq!("from_hex", error.operation);
+            }
+            other => panic!("expected step error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_file_round_trip() {
+        let recipe = "from_base64 url | to_hex | rot 5 | xor 0x20"
+            .parse::<Recipe>()
+            .unwrap();
+        let dir = std::env::temp_dir();
+
+        for name in ["recipe.json", "recipe.toml", "recipe.txt"] {
+            let path = dir.join(format!("cryptotop_test_{}_{name}", std::process::id()));
+
+            recipe.save(&path).unwrap();
+            let loaded = Recipe::load(&path);
+            std::fs::remove_file(&path).unwrap();
+
+            assert_eq!(recipe, loaded.unwrap(), "{name}");
+        }
+    }
+
+    #[test]
+    fn test_load_checks_arguments() {
+        let path =
+            std::env::temp_dir().join(format!("cryptotop_test_{}_bad.json", std::process::id()));
+        std::fs::write(
+            &path,
+            r#"{"steps": [{"op": "to_base64", "alphabet": "abc"}]}"#,
+        )
+        .unwrap();
+
+        let loaded = Recipe::load(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(matches!(loaded, Err(RecipeError::InvalidArguments { .. })));
+    }
+}
@@ -0,0 +1,116 @@
+// Shared --scorer and --lang options for every subcommand that ranks brute
+// force results.
+use clap::{Arg, ArgMatches};
+use english_recognition::bytes;
+use english_recognition::language::{find_language, Language};
+use english_recognition::ngram::NgramModel;
+use english_recognition::scorer::{
+    Bhattacharyya, ChiSquared, Composite, Dictionary, Pattern, Printable, Scorer,
+};
+use english_recognition::segment;
+use std::path::Path;
+
+use crate::exit_with_error;
+
+pub fn args(default: &'static str) -> Vec<Arg<'static, 'static>> {
+    vec![
+        Arg::with_name("scorer")
+            .long("scorer")
+            .takes_value(true)
+            .possible_values(&[
+                "frequency",
+                "chi-squared",
+                "ngram",
+                "words",
+                "segment",
+                "printable",
+                "bytes",
+                "pattern",
+                "composite",
+            ])
+            .default_value(default)
+            .help("How to rank candidates, composite mixes letter frequency, words, printable and flags, segment finds english words without spaces (english only), bytes judges every byte including punctuation and binary"),
+        Arg::with_name("pattern")
+            .long("pattern")
+            .takes_value(true)
+            .value_name("REGEX")
+            .help("Regex the pattern scorer looks for, defaults to flag formats like flag{...}"),
+        lang_arg(),
+        Arg::with_name("model")
+            .long("model")
+            .takes_value(true)
+            .value_name("PATH")
+            .help("N-gram model from 'model train' for the ngram scorer, which it becomes unless --scorer says otherwise"),
+    ]
+}
+
+// --lang on its own for subcommands that compare against a language without
+// ranking anything.
+pub fn lang_arg() -> Arg<'static, 'static> {
+    Arg::with_name("lang")
+        .long("lang")
+        .takes_value(true)
+        .value_name("LANGUAGE")
+        .default_value("en")
+        .validator(|x| match find_language(&x) {
+            Some(_) => Ok(()),
+            None => Err(format!(
+                "unknown language {x}, expected one of en, fr, de, es, nl or their names"
+            )),
+        })
+        .help("Language the plaintext is expected in")
+}
+
+pub fn lang(matches: &ArgMatches) -> &'static Language {
+    find_language(matches.value_of("lang").unwrap()).unwrap()
+}
+
+// Name of the chosen scorer for reports.
+pub fn name<'a>(matches: &'a ArgMatches) -> &'a str {
+    name_or(matches, matches.value_of("scorer").unwrap())
+}
+
+// Same when the default depends on other arguments, which a subcommand works
+// out for itself. It replaces the default given to args.
+pub fn name_or<'a>(matches: &'a ArgMatches, default: &'a str) -> &'a str {
+    match matches.occurrences_of("scorer") {
+        0 if matches.is_present("model") => "ngram",
+        0 => default,
+        _ => matches.value_of("scorer").unwrap(),
+    }
+}
+
+pub fn scorer(matches: &ArgMatches) -> Box<dyn Scorer + Sync> {
+    scorer_or(matches, matches.value_of("scorer").unwrap())
+}
+
+// Same with a default worked out by the subcommand, see name_or.
+pub fn scorer_or(matches: &ArgMatches, default: &str) -> Box<dyn Scorer + Sync> {
+    let pattern = || match matches.value_of("pattern") {
+        Some(pattern) => Pattern::new(pattern).unwrap_or_else(|e| exit_with_error(e)),
+        None => Pattern::flag(),
+    };
+
+    let lang = lang(matches);
+
+    match name_or(matches, default) {
+        "chi-squared" => Box::new(ChiSquared(lang)),
+        "ngram" => match matches.value_of("model") {
+            Some(path) => {
+                Box::new(NgramModel::load(Path::new(path)).unwrap_or_else(|e| exit_with_error(e)))
+            }
+            None => Box::new(lang.ngram_model()),
+        },
+        "words" => Box::new(Dictionary::language(lang)),
+        "segment" if lang.code != "en" => exit_with_error(format!(
+            "the segment scorer only knows english words, try --scorer words for {}",
+            lang.name
+        )),
+        "segment" => Box::new(segment::english()),
+        "printable" => Box::new(Printable),
+        "bytes" => Box::new(bytes::english()),
+        "pattern" => Box::new(pattern()),
+        "composite" => Box::new(Composite::language(lang, pattern())),
+        _ => Box::new(Bhattacharyya(lang)),
+    }
+}
@@ -0,0 +1,586 @@
+// End to end tests of the cryptotop binary, covering the shared input and
+// output layers and how the report subcommands use them.
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{self, Command, Output, Stdio};
+
+const HELLO_XOR_58: &str = "1b37373331363f78151b7f2b783431333d78397828372d363c78373e783a393b3736";
+
+const DICKENS: &str = "It was the best of times, it was the worst of times, it was the age of \
+                       wisdom, it was the age of foolishness, it was the epoch of belief, it \
+                       was the epoch of incredulity";
+
+// Run cryptotop with stdin piped in, stdout is never a terminal here so auto
+// format writes raw bytes.
+fn run(args: &[&str], stdin: &[u8]) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_cryptotop"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    // commands given their input as an argument may exit without reading stdin
+    let _ = child.stdin.take().unwrap().write_all(stdin);
+
+    child.wait_with_output().unwrap()
+}
+
+// Stdout of a run that has to succeed.
+fn stdout(args: &[&str], stdin: &[u8]) -> Vec<u8> {
+    let output = run(args, stdin);
+    assert!(
+        output.status.success(),
+        "{args:?} failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    output.stdout
+}
+
+fn text(args: &[&str], stdin: &[u8]) -> String {
+    String::from_utf8(stdout(args, stdin)).unwrap()
+}
+
+// Stderr of a run that has to fail cleanly rather than panic.
+fn error(args: &[&str], stdin: &[u8]) -> String {
+    let output = run(args, stdin);
+    assert_eq!(
+        Some(1),
+        output.status.code(),
+        "{args:?} didn't fail cleanly"
+    );
+
+    String::from_utf8(output.stderr).unwrap()
+}
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("cryptotop-cli-{}-{name}", process::id()))
+}
+
+#[test]
+fn test_input_sources() {
+    assert_eq!("4869", text(&["hex", "encode", "Hi"], b""));
+    assert_eq!("4869", text(&["hex", "encode", "-"], b"Hi"));
+    assert_eq!("4869", text(&["hex", "encode"], b"Hi"));
+
+    let path = temp_path("input");
+    fs::write(&path, b"Hi").unwrap();
+    let encoded = text(&["hex", "encode", "--file", path.to_str().unwrap()], b"");
+    fs::remove_file(&path).unwrap();
+    assert_eq!("4869", encoded);
+
+    assert!(
+        error(&["hex", "encode", "--file", "/nonexistent/cryptotop"], b"")
+            .starts_with("error: couldn't open /nonexistent/cryptotop")
+    );
+}
+
+#[test]
+fn test_input_decoding() {
+    assert_eq!(
+        b"Hi".to_vec(),
+        stdout(&["hex", "decode", "--in-b64", "NDg2OQ=="], b"")
+    );
+    assert_eq!(
+        b"Hi".to_vec(),
+        stdout(&["hex", "decode", "--in-b64"], b"NDg2\nOQ==\n")
+    );
+    assert_eq!("SGk=", text(&["base64", "encode", "--in-hex"], b"4869\n"));
+
+    // literal input points at the bad symbol
+    let stderr = error(&["hex", "encode", "--in-hex", "48g9"], b"");
+    assert_eq!(
+        "error: invalid hex symbol 'g' at offset 2\n    48g9\n      ^\n",
+        stderr
+    );
+}
+
+#[test]
+fn test_trailing_newline_decode() {
+    assert_eq!(b"Hi".to_vec(), stdout(&["base64", "decode"], b"SGk=\n"));
+    assert_eq!(b"Hi".to_vec(), stdout(&["hex", "decode"], b"4869\r\n"));
+
+    // only trailing whitespace is dropped
+    assert!(error(&["hex", "decode"], b"48\n69\n").starts_with("error: invalid hex symbol"));
+}
+
+#[test]
+fn test_output_formats() {
+    assert_eq!(
+        "4869\n",
+        text(&["hex", "decode", "4869", "--format", "hex"], b"")
+    );
+    assert_eq!(
+        "SGk=\n",
+        text(&["hex", "decode", "4869", "--format", "b64"], b"")
+    );
+    assert_eq!(
+        "[72, 105]\n",
+        text(&["hex", "decode", "4869", "--format", "debug"], b"")
+    );
+    assert_eq!(
+        "Hi\n",
+        text(&["hex", "decode", "4869", "--format", "text"], b"")
+    );
+    assert_eq!(
+        b"Hi".to_vec(),
+        stdout(&["hex", "decode", "4869", "--raw"], b"")
+    );
+    assert_eq!(b"Hi".to_vec(), stdout(&["hex", "decode", "4869"], b""));
+
+    // streamed results go through the same formats
+    assert_eq!(
+        "4869\n",
+        text(&["hex", "decode", "--format", "hex"], b"4869")
+    );
+
+    assert!(
+        error(&["hex", "decode", "4869", "--raw", "--format", "hex"], b"")
+            .contains("cannot be used with")
+    );
+}
+
+#[test]
+fn test_output_file() {
+    let path = temp_path("output");
+    let written = stdout(
+        &["hex", "decode", "4869", "--out", path.to_str().unwrap()],
+        b"",
+    );
+    let contents = fs::read(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert!(written.is_empty());
+    assert_eq!(b"Hi".to_vec(), contents);
+}
+
+#[test]
+fn test_report_raw() {
+    // raw output from a report is only the best result, ready to pipe on
+    assert_eq!("Hello", text(&["caesar", "crack", "Uryyb", "--raw"], b""));
+    assert_eq!(
+        "Cooking MC's like a pound of bacon",
+        text(&["xor", "single", "--in-hex", HELLO_XOR_58, "--raw"], b"")
+    );
+
+    let ciphertext = stdout(&["xor", "encrypt", "--key", "secret", DICKENS], b"");
+    assert_eq!(DICKENS, text(&["xor", "break", "--raw"], &ciphertext));
+    assert_eq!(
+        "Hello, World! This is a test",
+        text(&["magic", "Uryyb, Jbeyq! Guvf vf n grfg", "--raw"], b"")
+    );
+}
+
+#[test]
+fn test_report_format() {
+    let report = text(
+        &["xor", "single", "--in-hex", HELLO_XOR_58, "--format", "hex"],
+        b"",
+    );
+    assert!(report.contains(&format!(
+        "for key 0x58:\n\t{}\n",
+        "436f6f6b696e67204d432773206c696b65206120706f756e64206f66206261636f6e"
+    )));
+
+    let ciphertext = stdout(&["xor", "encrypt", "--key", "secret", DICKENS], b"");
+    let report = text(&["xor", "break", "--format", "b64"], &ciphertext);
+    assert!(report.contains("Recovered 6 byte key"));
+    assert!(report
+        .lines()
+        .any(|x| x.starts_with("6\t") && x.ends_with("\tchosen")));
+    assert!(report.contains("\tsecret\n"));
+    assert!(report.contains(&text(&["base64", "encode", DICKENS], b"")));
+}
+
+#[test]
+fn test_xor_break_scorer() {
+    let ciphertext = stdout(&["xor", "encrypt", "--key", "secret", DICKENS], b"");
+
+    let report = text(&["xor", "break", "--scorer", "bytes"], &ciphertext);
+    assert_eq!(text(&["xor", "break"], &ciphertext), report);
+    assert!(report.contains("confidence by bytes score:\n\tsecret\n"));
+
+    // printable can't tell one printable column from another, so a worse key
+    // shows the ranking really is the chosen scorer's
+    let report = text(&["xor", "break", "--scorer", "printable"], &ciphertext);
+    assert!(report.contains("confidence by printable score:"));
+    assert!(!report.contains("\tsecret\n"));
+
+    assert!(error(
+        &["xor", "break", "--scorer", "segment", "--lang", "fr"],
+        &ciphertext
+    )
+    .contains("segment scorer only knows english"));
+}
+
+#[test]
+fn test_analyze_lang() {
+    let report = text(&["analyze", "--lang", "fr", "Bonjour tout le monde"], b"");
+    assert!(report.contains("(french 0.0769, random 0.0385)"));
+}
+
+#[test]
+fn test_empty_input() {
+    assert_eq!("", text(&["hex", "encode"], b""));
+    assert_eq!("", text(&["base64", "encode"], b""));
+    assert_eq!("", text(&["hex", "decode"], b""));
+    assert_eq!("", text(&["base64", "decode"], b"\n"));
+
+    let report = text(&["analyze"], b"");
+    assert!(report.contains("Shannon entropy: 0.0000 bits per byte, 0.0000 bits per character"));
+    assert!(report.contains("No letters to compare to english."));
+    assert!(error(&["caesar", "crack", "--raw"], b"").contains("every candidate scores the same"));
+    assert_eq!("", text(&["xor", "single", "--raw"], b""));
+    assert!(error(&["xor", "break"], b"").contains("too short to break"));
+
+    assert!(text(&["magic"], b"").starts_with("Best 0 of 0 decodings"));
+    assert_eq!(
+        "error: no decodings of the input to write\n",
+        error(&["magic", "--raw"], b"")
+    );
+}
+
+#[test]
+fn test_recipe() {
+    assert_eq!(
+        b"Hello".to_vec(),
+        stdout(
+            &[
+                "recipe",
+                "--recipe",
+                "from_base64 | from_hex",
+                "NDg2NTZjNmM2Zg=="
+            ],
+            b""
+        )
+    );
+
+    // a loaded recipe still takes inline input
+    let path = temp_path("recipe.txt");
+    stdout(
+        &[
+            "recipe",
+            "--recipe",
+            "from_hex | rot 13",
+            "--save",
+            path.to_str().unwrap(),
+        ],
+        b"",
+    );
+    let result = stdout(
+        &["recipe", "--load", path.to_str().unwrap(), "48656c6c6f"],
+        b"",
+    );
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(b"Uryyb".to_vec(), result);
+}
+
+#[test]
+fn test_caesar_spaced_format() {
+    // the spaced candidate is rendered once, like the candidate above it
+    let report = text(
+        &["caesar", "crack", "AOLYLPZUVZWVVU", "--format", "hex"],
+        b"",
+    );
+    assert!(report.contains(&format!(
+        "\t{}\n\t{}\n",
+        text(&["hex", "encode", "THEREISNOSPOON"], b""),
+        text(&["hex", "encode", "THERE IS NO SPOON"], b"")
+    )));
+
+    assert!(error(
+        &["caesar", "crack", "Uryyb", "--scorer", "segment", "--lang", "fr"],
+        b""
+    )
+    .contains("segment scorer only knows english"));
+}
+
+#[test]
+fn test_xor_detect() {
+    let lines = format!(
+        "0e3647e8592d35514a081243582536ed3de6734059001e3f535ce6271032\n\
+         not hex at all\n\
+         \n\
+         {HELLO_XOR_58}\n\
+         334b1e5c2a9c44db8f31d8f6e1ea9e1fd3c26d06c8a0d7c3ff3f64b79e48\n"
+    );
+
+    // bad lines are skipped with a warning, the rest are still searched
+    let output = run(&["xor", "detect"], lines.as_bytes());
+    assert!(output.status.success());
+    assert_eq!(
+        "warning: skipping line 2: invalid hex symbol 'n' at offset 0\n",
+        String::from_utf8(output.stderr).unwrap()
+    );
+    let report = String::from_utf8(output.stdout).unwrap();
+    assert!(report.starts_with("Best single byte XOR of 3 lines, ordered by bytes score:"));
+    assert!(report.contains("on line 4 with key 0x58"));
+
+    let path = temp_path("detect.txt");
+    fs::write(&path, &lines).unwrap();
+    let best = stdout(
+        &[
+            "xor",
+            "detect",
+            "--file",
+            path.to_str().unwrap(),
+            "--scorer",
+            "frequency",
+            "--raw",
+        ],
+        b"",
+    );
+    fs::remove_file(&path).unwrap();
+    assert_eq!(b"Cooking MC's like a pound of bacon".to_vec(), best);
+
+    assert!(error(&["xor", "detect"], b"zz\n").ends_with("error: no lines to check\n"));
+}
+
+#[test]
+fn test_xor_crib() {
+    let first =
+        "1f5b1c0102011b060a4d094116561d54140a194d0146145100541d13041f4b4711445318131f184d0f5c1e";
+    let second =
+        "0a470d40101f5204154d0f520e4f53151c0141000e560d011200521109084b41105716065207000300";
+
+    let report = text(
+        &[
+            "xor", "crib", first, second, "--crib", " the ", "--top", "1",
+        ],
+        b"",
+    );
+    assert_eq!(
+        "Dragged \" the \" over 37 offsets, 2 plausible:\n\n*-1.3095\t1^2\toffset 26\tover \n",
+        report
+    );
+
+    // ciphertexts can come one per line from stdin like any other input
+    let lines = format!("{first}\n\n{second}\n");
+    let piped = text(
+        &["xor", "crib", "--crib", " the ", "--top", "1"],
+        lines.as_bytes(),
+    );
+    assert_eq!(report, piped);
+
+    // --in-hex says what crib assumes anyway, --in-b64 reads base64 instead,
+    // each line decoded once
+    let args = ["xor", "crib", "--crib", " the ", "--top", "1"];
+    let hex = text(&[&args[..], &["--in-hex", first, second]].concat(), b"");
+    assert_eq!(report, hex);
+    assert_eq!(
+        report,
+        text(&[&args[..], &["--in-hex"]].concat(), lines.as_bytes())
+    );
+
+    let encode = |x: &str| {
+        text(&["base64", "encode", "--in-hex", x], b"")
+            .trim()
+            .to_string()
+    };
+    let (first_b64, second_b64) = (encode(first), encode(second));
+    let b64 = text(
+        &[&args[..], &["--in-b64", &first_b64, &second_b64]].concat(),
+        b"",
+    );
+    assert_eq!(report, b64);
+    let b64_lines = format!("{}\n{}", first_b64, second_b64);
+    assert_eq!(
+        report,
+        text(&[&args[..], &["--in-b64"]].concat(), b64_lines.as_bytes())
+    );
+    assert!(error(
+        &[&args[..], &["--in-b64", "b64!", &second_b64]].concat(),
+        b""
+    )
+    .contains("b64!"));
+
+    assert!(error(&["xor", "crib", "-i"], lines.as_bytes()).contains("reads commands from stdin"));
+    assert!(
+        error(&["xor", "crib", first, second, "-i", "--crib", "x"], b"")
+            .contains("cannot be used with")
+    );
+}
+
+#[test]
+fn test_caesar_alphabet_scorer() {
+    // rotating punctuation and digits ranks by the bytes scorer by default
+    let ciphertext = stdout(
+        &[
+            "caesar",
+            "encrypt",
+            "--alphabet",
+            "printable",
+            "--shift",
+            "20",
+            "Hello, World! 123",
+        ],
+        b"",
+    );
+    let report = text(&["caesar", "crack", "--alphabet", "printable"], &ciphertext);
+    assert!(report.contains("Ordered by english language bytes score:"));
+    assert_eq!(
+        "Hello, World! 123",
+        text(
+            &["caesar", "crack", "--alphabet", "printable", "--raw"],
+            &ciphertext
+        )
+    );
+
+    let report = text(
+        &[
+            "caesar",
+            "crack",
+            "--alphabet",
+            "printable",
+            "--scorer",
+            "frequency",
+        ],
+        &ciphertext,
+    );
+    assert!(report.contains("Ordered by english language frequency score:"));
+}
+
+#[test]
+fn test_caesar_alphabets() {
+    assert_eq!(
+        "Hello",
+        text(
+            &["caesar", "crack", "--alphabet", "letters", "Uryyb", "--raw"],
+            b""
+        )
+    );
+
+    // no scorer sees cyrillic, so the shifts are listed rather than ranked
+    let report = text(
+        &["caesar", "crack", "--alphabet", "cyrillic", "Привет"],
+        b"",
+    );
+    assert!(report.contains(
+        "Every shift scores the same by frequency, listed in order:\n0\tПривет\n1\tОпзбдс\n"
+    ));
+    assert!(!report.contains("Highest score"));
+    assert!(error(
+        &["caesar", "crack", "--alphabet", "greek", "--raw", "Γεια"],
+        b""
+    )
+    .contains("every candidate scores the same by frequency"));
+}
+
+#[test]
+fn test_caesar_subcommands() {
+    // ciphertexts named like a subcommand are only ambiguous without crack
+    assert!(text(&["caesar", "crack", "decrypt"], b"")
+        .starts_with("Brute force results for: decrypt\n"));
+    assert_eq!(
+        "Uryyb",
+        text(&["caesar", "encrypt", "--shift", "13", "Hello"], b"")
+    );
+    assert_eq!(
+        "Hello",
+        text(&["caesar", "decrypt", "--shift", "13", "Uryyb"], b"")
+    );
+}
+
+#[test]
+fn test_caesar_wordlist() {
+    // both keywords mix z, e, b, r and a to the front, so they decrypt this
+    // to the same plaintexts and each pair has to keep its own keyword
+    let path = temp_path("keywords.txt");
+    fs::write(&path, "zebra\nzebrat\n").unwrap();
+    let report = text(
+        &[
+            "caesar",
+            "crack",
+            "--wordlist",
+            path.to_str().unwrap(),
+            "--top",
+            "2",
+            "zebra",
+        ],
+        b"",
+    );
+    fs::remove_file(&path).unwrap();
+
+    assert!(report.starts_with("Keyed brute force of 52 keyword and shift pairs"));
+    assert!(report.ends_with("\tzebra\t22\tefghi\n0.5180\tzebrat\t22\tefghi\n"));
+}
+
+#[test]
+fn test_caesar_unusable_keyword() {
+    for keyword in ["123", "ÉÉ"] {
+        assert_eq!(
+            format!("error: keyword \"{keyword}\" has none of the caesar alphabet's symbols\n"),
+            error(
+                &[
+                    "caesar",
+                    "encrypt",
+                    "--shift",
+                    "1",
+                    "--keyword",
+                    keyword,
+                    "abc"
+                ],
+                b""
+            )
+        );
+    }
+    assert!(error(&["caesar", "crack", "--keyword", "123", "abc"], b"")
+        .contains("none of the caesar alphabet's symbols"));
+
+    let path = temp_path("digit_keywords.txt");
+    fs::write(&path, "123\n456\n").unwrap();
+    let report = error(
+        &[
+            "caesar",
+            "crack",
+            "--wordlist",
+            path.to_str().unwrap(),
+            "abc",
+        ],
+        b"",
+    );
+    fs::remove_file(&path).unwrap();
+
+    assert!(report.contains("has no keywords with any of the alphabet's symbols"));
+}
+
+#[test]
+fn test_affine_crack() {
+    let ciphertext = text(
+        &[
+            "affine",
+            "encrypt",
+            "-a",
+            "5",
+            "-b",
+            "8",
+            "The quick brown fox jumps over the lazy dog",
+        ],
+        b"",
+    );
+    assert_eq!(
+        "The quick brown fox jumps over the lazy dog",
+        text(&["affine", "crack", "--raw", &ciphertext], b"")
+    );
+    assert!(text(&["affine", "crack", "recover"], b"")
+        .starts_with("Brute force results for: recover\n"));
+
+    // without letters every key gives the same plaintext, so none is best and
+    // every key is listed in order
+    let report = text(&["affine", "crack", "--top", "2", "1 2"], b"");
+    assert!(report.contains(
+        "Every key scores the same by frequency, listed in order:\na=1 b=0\t1 2\na=1 b=1\t1 2\n"
+    ));
+    assert_eq!(312, report.lines().filter(|x| x.ends_with("\t1 2")).count());
+
+    for input in ["", "1 2"] {
+        assert_eq!(
+            "error: every candidate scores the same by frequency, there's no best one to write\n",
+            error(&["affine", "crack", "--raw", input], b"")
+        );
+    }
+}
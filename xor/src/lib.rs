@@ -0,0 +1,602 @@
+pub mod xor {
+    use english_recognition::bytes::{self, ByteModel};
+    use english_recognition::scorer::{descending, Scorer};
+    use english_recognition::statistics::byte_index_of_coincidence;
+    use rayon::prelude::*;
+
+    // Key sizes fitting within this share of the best fit are worth solving.
+    const KEY_SIZE_TOLERANCE: f64 = 0.6;
+
+    // Multiples of the real key size decrypt as well or a touch better by
+    // fitting each column on fewer bytes, so the smallest size whose plaintext
+    // scores within this many log10 per byte of the best wins.
+    const PLAINTEXT_MARGIN: f64 = 0.05;
+
+    // Bits that differ between two bytes of random data on average.
+    const RANDOM_DISTANCE: f64 = 4.0;
+
+    // Byte model score per byte from which a crib drag result counts as
+    // plausible english, prose is around -1.4, printable gibberish around
+    // -1.8 and binary far lower.
+    const PLAUSIBLE_SCORE: f64 = -1.6;
+
+    // Every byte of the data XORed with the same key byte.
+    pub fn single_byte(data: &[u8], key: u8) -> Vec<u8> {
+        data.iter().map(|x| x ^ key).collect()
+    }
+
+    // Every single byte key with the plaintext it gives, most english first by
+    // the byte level model so spaces and punctuation count and binary sinks.
+    pub fn brute_force_single_byte(ciphertext: &[u8]) -> Vec<(u8, Vec<u8>)> {
+        brute_force_single_byte_with(ciphertext, bytes::english())
+            .into_iter()
+            .map(|(key, plaintext, _)| (key, plaintext))
+            .collect()
+    }
+
+    // Same ranked by any scorer, keeping each score. Ties go to the lower key.
+    pub fn brute_force_single_byte_with(
+        ciphertext: &[u8],
+        scorer: &dyn Scorer,
+    ) -> Vec<(u8, Vec<u8>, f64)> {
+        let mut candidates = (0..=255)
+            .map(|key| {
+                let plaintext = single_byte(ciphertext, key);
+                let score = scorer.score_bytes(&plaintext);
+                (key, plaintext, score)
+            })
+            .collect::<Vec<(u8, Vec<u8>, f64)>>();
+
+        candidates.sort_by(|a, b| descending(a.2, b.2).then(a.0.cmp(&b.0)));
+
+        candidates
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct Detection {
+        // Position of the candidate in the ones searched.
+        pub index: usize,
+        pub key: u8,
+        pub plaintext: Vec<u8>,
+        pub score: f64,
+    }
+
+    // Best single byte key for every candidate, most english first, for
+    // finding the one line of many that was XORed. Candidates are brute
+    // forced in parallel.
+    pub fn detect_single_byte(candidates: &[Vec<u8>]) -> Vec<Detection> {
+        detect_single_byte_with(candidates, bytes::english())
+    }
+
+    // Same ranked by any scorer that can be shared between threads.
+    pub fn detect_single_byte_with(
+        candidates: &[Vec<u8>],
+        scorer: &(dyn Scorer + Sync),
+    ) -> Vec<Detection> {
+        let mut detections = candidates
+            .par_iter()
+            .enumerate()
+            .map(|(index, ciphertext)| {
+                let (key, plaintext, score) = brute_force_single_byte_with(ciphertext, scorer)
+                    .into_iter()
+                    .next()
+                    .unwrap();
+
+                Detection {
+                    index,
+                    key,
+                    plaintext,
+                    score,
+                }
+            })
+            .collect::<Vec<Detection>>();
+
+        detections.sort_by(|a, b| descending(a.score, b.score).then(a.index.cmp(&b.index)));
+
+        detections
+    }
+
+    // Data XORed with the key repeated along it, which both encrypts and
+    // decrypts. An empty key leaves the data as it is.
+    pub fn repeating_key(data: &[u8], key: &[u8]) -> Vec<u8> {
+        if key.is_empty() {
+            return data.to_vec();
+        }
+
+        data.iter()
+            .zip(key.iter().cycle())
+            .map(|(x, k)| x ^ k)
+            .collect()
+    }
+
+    // Number of bits that differ.
+    pub fn hamming_distance(a: &[u8], b: &[u8]) -> u32 {
+        a.iter().zip(b).map(|(x, y)| (x ^ y).count_ones()).sum()
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct KeySize {
+        pub size: usize,
+        // Hamming distance between neighbouring blocks of the key size in bits
+        // per byte, lower when they were XORed with the same key.
+        pub distance: f64,
+        // Average byte index of coincidence of the columns each key byte
+        // covers, higher when every column shares one key byte.
+        pub coincidence: f64,
+    }
+
+    impl KeySize {
+        // How much more structured than random data the blocks and columns
+        // look, higher is a more likely key size.
+        pub fn fit(&self) -> f64 {
+            let distance = (RANDOM_DISTANCE / self.distance.max(f64::EPSILON)).min(RANDOM_DISTANCE);
+
+            self.coincidence * 256.0 * distance
+        }
+    }
+
+    // Every key size from 1 up to max_size that leaves at least two blocks,
+    // best fit first.
+    pub fn key_sizes(ciphertext: &[u8], max_size: usize) -> Vec<KeySize> {
+        let mut sizes = (1..=max_size.min(ciphertext.len() / 2))
+            .map(|size| {
+                let blocks = ciphertext.chunks_exact(size).collect::<Vec<&[u8]>>();
+                let distance = blocks
+                    .windows(2)
+                    .map(|x| hamming_distance(x[0], x[1]) as f64 / size as f64)
+                    .sum::<f64>()
+                    / (blocks.len() - 1) as f64;
+
+                let coincidence = columns(ciphertext, size)
+                    .iter()
+                    .map(|x| byte_index_of_coincidence(x))
+                    .sum::<f64>()
+                    / size as f64;
+
+                KeySize {
+                    size,
+                    distance,
+                    coincidence,
+                }
+            })
+            .collect::<Vec<KeySize>>();
+
+        sizes.sort_by(|a, b| descending(a.fit(), b.fit()).then(a.size.cmp(&b.size)));
+
+        sizes
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct Recovered {
+        pub key: Vec<u8>,
+        pub plaintext: Vec<u8>,
+        // Chance each key byte is right under the model, averaged over the
+        // key, 0 to 1.
+        pub confidence: f64,
+        // Every key size considered, best fit first as key_sizes gives them.
+        pub key_sizes: Vec<KeySize>,
+        // How many of the best fitting key sizes were solved, those within
+        // KEY_SIZE_TOLERANCE of the best fit. The key is the smallest of them
+        // whose plaintext scores about as well as the best.
+        pub solved: usize,
+    }
+
+    // Recover a repeating XOR key of up to max_size bytes by the english byte
+    // model, None when the ciphertext is too short to have two blocks.
+    pub fn break_repeating_key(ciphertext: &[u8], max_size: usize) -> Option<Recovered> {
+        break_repeating_key_with(ciphertext, max_size, bytes::english())
+    }
+
+    // Same ranked by any scorer, e.g. a byte model trained on the kind of
+    // plaintext expected. Confidence and PLAINTEXT_MARGIN assume log10
+    // likelihoods per byte like the byte models give, other scorers still
+    // rank keys but their confidence means less.
+    pub fn break_repeating_key_with(
+        ciphertext: &[u8],
+        max_size: usize,
+        scorer: &dyn Scorer,
+    ) -> Option<Recovered> {
+        let key_sizes = key_sizes(ciphertext, max_size);
+        let best_fit = key_sizes.first().map_or(0.0, KeySize::fit);
+        let count = key_sizes
+            .iter()
+            .take_while(|x| x.fit() >= best_fit * KEY_SIZE_TOLERANCE)
+            .count();
+
+        let mut solved = key_sizes[..count]
+            .iter()
+            .map(|x| {
+                let (key, confidence) = solve(ciphertext, x.size, scorer);
+                let plaintext = repeating_key(ciphertext, &key);
+                let score = scorer.score_bytes(&plaintext);
+                (key, plaintext, confidence, score)
+            })
+            .collect::<Vec<(Vec<u8>, Vec<u8>, f64, f64)>>();
+
+        let best_score = solved.iter().fold(f64::NEG_INFINITY, |a, b| a.max(b.3));
+        solved.retain(|(_, _, _, score)| *score >= best_score - PLAINTEXT_MARGIN);
+        solved.sort_by_key(|(key, _, _, _)| key.len());
+
+        let (key, plaintext, confidence, _) = solved.into_iter().next()?;
+
+        Some(Recovered {
+            key,
+            plaintext,
+            confidence,
+            key_sizes,
+            solved: count,
+        })
+    }
+
+    // Best key of the size, each byte solved on its own column, with the
+    // chance each byte is right averaged over the key.
+    fn solve(ciphertext: &[u8], size: usize, scorer: &dyn Scorer) -> (Vec<u8>, f64) {
+        let mut key = Vec::with_capacity(size);
+        let mut confidence = 0.0;
+
+        for column in columns(ciphertext, size) {
+            let candidates = brute_force_single_byte_with(&column, scorer);
+            key.push(candidates[0].0);
+
+            // scores are log10 likelihoods per byte, weigh every key by how
+            // likely it makes the whole column
+            let length = column.len() as f64;
+            let best = candidates[0].2 * length;
+            let total = candidates
+                .iter()
+                .map(|(_, _, score)| 10_f64.powf(score * length - best))
+                .sum::<f64>();
+            confidence += 1.0 / total;
+        }
+
+        (key, confidence / size as f64)
+    }
+
+    // Two byte strings XORed together as far as the shorter one goes. With
+    // the same keystream on both the key cancels out, leaving the plaintexts
+    // XORed together.
+    pub fn xor_bytes(a: &[u8], b: &[u8]) -> Vec<u8> {
+        a.iter().zip(b).map(|(x, y)| x ^ y).collect()
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct CribMatch {
+        // Ciphertexts whose XOR the crib was dragged over, by position.
+        pub pair: (usize, usize),
+        pub offset: usize,
+        // What the other plaintext holds at the offset if either holds the
+        // crib there.
+        pub text: Vec<u8>,
+        pub score: f64,
+    }
+
+    impl CribMatch {
+        pub fn is_plausible(&self) -> bool {
+            self.score >= PLAUSIBLE_SCORE
+        }
+    }
+
+    // Slide the crib over every offset of every pair of ciphertexts that
+    // shared a keystream, most english first.
+    pub fn drag_crib(ciphertexts: &[Vec<u8>], crib: &[u8]) -> Vec<CribMatch> {
+        drag_crib_with(ciphertexts, crib, bytes::english())
+    }
+
+    // Same with any byte model.
+    pub fn drag_crib_with(
+        ciphertexts: &[Vec<u8>],
+        crib: &[u8],
+        model: &ByteModel,
+    ) -> Vec<CribMatch> {
+        let mut matches = Vec::new();
+        if crib.is_empty() {
+            return matches;
+        }
+
+        for i in 0..ciphertexts.len() {
+            for j in i + 1..ciphertexts.len() {
+                let combined = xor_bytes(&ciphertexts[i], &ciphertexts[j]);
+
+                for (offset, window) in combined.windows(crib.len()).enumerate() {
+                    let text = xor_bytes(window, crib);
+                    let score = model.score(&text);
+
+                    matches.push(CribMatch {
+                        pair: (i, j),
+                        offset,
+                        text,
+                        score,
+                    });
+                }
+            }
+        }
+
+        matches.sort_by(|a, b| {
+            descending(a.score, b.score)
+                .then(a.pair.cmp(&b.pair))
+                .then(a.offset.cmp(&b.offset))
+        });
+
+        matches
+    }
+
+    // Ciphertexts that reused one keystream, with the keystream bytes worked
+    // out so far pinned so every plaintext can be shown as far as it's known.
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct Workbench {
+        ciphertexts: Vec<Vec<u8>>,
+        keystream: Vec<Option<u8>>,
+    }
+
+    impl Workbench {
+        pub fn new(ciphertexts: Vec<Vec<u8>>) -> Workbench {
+            let longest = ciphertexts.iter().map(|x| x.len()).max().unwrap_or(0);
+
+            Workbench {
+                ciphertexts,
+                keystream: vec![None; longest],
+            }
+        }
+
+        pub fn ciphertexts(&self) -> &[Vec<u8>] {
+            &self.ciphertexts
+        }
+
+        pub fn keystream(&self) -> &[Option<u8>] {
+            &self.keystream
+        }
+
+        // Pin keystream bytes from the offset, anything past the longest
+        // ciphertext is dropped.
+        pub fn pin_key(&mut self, offset: usize, key: &[u8]) {
+            for (slot, byte) in self.keystream.iter_mut().skip(offset).zip(key) {
+                *slot = Some(*byte);
+            }
+        }
+
+        // Pin the keystream that makes a ciphertext read as the plaintext at
+        // the offset, e.g. a crib that turned out to fit. False if the
+        // ciphertext doesn't exist.
+        pub fn pin_plaintext(&mut self, index: usize, offset: usize, plaintext: &[u8]) -> bool {
+            let Some(ciphertext) = self.ciphertexts.get(index) else {
+                return false;
+            };
+
+            let key = xor_bytes(ciphertext.get(offset..).unwrap_or_default(), plaintext);
+            self.pin_key(offset, &key);
+            true
+        }
+
+        pub fn unpin(&mut self, offset: usize, length: usize) {
+            for slot in self.keystream.iter_mut().skip(offset).take(length) {
+                *slot = None;
+            }
+        }
+
+        // Every ciphertext decrypted as far as the keystream is known.
+        pub fn plaintexts(&self) -> Vec<Vec<Option<u8>>> {
+            self.ciphertexts
+                .iter()
+                .map(|ciphertext| {
+                    ciphertext
+                        .iter()
+                        .zip(&self.keystream)
+                        .map(|(byte, key)| key.map(|key| byte ^ key))
+                        .collect()
+                })
+                .collect()
+        }
+    }
+
+    // Bytes each position of a key of the size is XORed with.
+    fn columns(data: &[u8], size: usize) -> Vec<Vec<u8>> {
+        let mut columns = vec![Vec::new(); size];
+        for (i, byte) in data.iter().enumerate() {
+            columns[i % size].push(*byte);
+        }
+
+        columns
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::xor::*;
+    use english_recognition::scorer::{Composite, Printable};
+
+    const PLAINTEXT: &[u8] = b"Cooking MC's like a pound of bacon";
+
+    const LONG_PLAINTEXT: &[u8] = b"It is a truth universally acknowledged, that a single man in \
+possession of a good fortune, must be in want of a wife. However little known the feelings or \
+views of such a man may be on his first entering a neighbourhood, this truth is so well fixed in \
+the minds of the surrounding families, that he is considered the rightful property of some one \
+or other of their daughters.";
+
+    #[test]
+    fn test_single_byte() {
+        assert_eq!(
+            vec![0x01, 0x00, 0xfe],
+            single_byte(&[0x00, 0x01, 0xff], 0x01)
+        );
+        assert_eq!(PLAINTEXT, single_byte(&single_byte(PLAINTEXT, 0x58), 0x58));
+        assert!(single_byte(&[], 0x20).is_empty());
+    }
+
+    #[test]
+    fn test_brute_force_single_byte() {
+        let ciphertext = single_byte(PLAINTEXT, 0x58);
+
+        let candidates = brute_force_single_byte(&ciphertext);
+
+        assert_eq!(256, candidates.len());
+        assert_eq!((0x58, PLAINTEXT.to_vec()), candidates[0]);
+    }
+
+    #[test]
+    fn test_brute_force_with_scorer() {
+        // many keys keep every byte printable, tied scores go to the lower key
+        let scorer = Composite::new().with(1.0, Printable);
+        let candidates = brute_force_single_byte_with(b"hello world", &scorer);
+
+        assert_eq!((0x00, b"hello world".to_vec(), 1.0), candidates[0]);
+        assert_eq!(1.0, candidates[1].2);
+        assert!(candidates[1].0 > 0x00);
+        assert!(candidates.windows(2).all(|x| x[0].2 >= x[1].2));
+    }
+
+    #[test]
+    fn test_repeating_key() {
+        let plaintext =
+            b"Burning 'em, if you ain't quick and nimble\nI go crazy when I hear a cymbal";
+
+        // the cryptopals set 1 challenge 5 vector
+        assert_eq!(
+            "0b3637272a2b2e63622c2e69692a23693a2a3c6324202d623d63343c2a26226324272765272a282b2f20430a652e2c652a3124333a653e2b2027630c692b20283165286326302e27282f",
+            repeating_key(plaintext, b"ICE")
+                .iter()
+                .map(|x| format!("{x:02x}"))
+                .collect::<String>()
+        );
+        assert_eq!(
+            plaintext.to_vec(),
+            repeating_key(&repeating_key(plaintext, b"ICE"), b"ICE")
+        );
+        assert_eq!(plaintext.to_vec(), repeating_key(plaintext, b""));
+    }
+
+    #[test]
+    fn test_hamming_distance() {
+        assert_eq!(37, hamming_distance(b"this is a test", b"wokka wokka!!!"));
+        assert_eq!(0, hamming_distance(b"", b""));
+    }
+
+    #[test]
+    fn test_key_sizes() {
+        let ciphertext = repeating_key(LONG_PLAINTEXT, b"secret");
+
+        let sizes = key_sizes(&ciphertext, 20);
+
+        assert_eq!(20, sizes.len());
+        // 6 or a multiple of it, break settles on the smallest
+        assert_eq!(0, sizes[0].size % 6);
+        assert!(key_sizes(b"a", 20).is_empty());
+    }
+
+    #[test]
+    fn test_break_repeating_key_sizes() {
+        let ciphertext = repeating_key(LONG_PLAINTEXT, b"secret");
+
+        let recovered = break_repeating_key(&ciphertext, 40).unwrap();
+
+        // every size ranked, the best few solved, among them the key's
+        assert_eq!(key_sizes(&ciphertext, 40), recovered.key_sizes);
+        assert!(recovered.solved > 0 && recovered.solved < recovered.key_sizes.len());
+        assert!(recovered.key_sizes[..recovered.solved]
+            .iter()
+            .any(|x| x.size == 6));
+    }
+
+    #[test]
+    fn test_break_repeating_key() {
+        for key in [b"k".as_slice(), b"secret", b"Vanilla Ice"] {
+            let ciphertext = repeating_key(LONG_PLAINTEXT, key);
+
+            let recovered = break_repeating_key(&ciphertext, 40).unwrap();
+
+            assert_eq!(key, recovered.key);
+            assert_eq!(LONG_PLAINTEXT, recovered.plaintext);
+            assert!(recovered.confidence > 0.9);
+        }
+
+        // columns of a dozen bytes still give the key size but can miss a byte
+        let key = b"Terminator X: Bring the noise";
+        let recovered = break_repeating_key(&repeating_key(LONG_PLAINTEXT, key), 40).unwrap();
+        let right = key
+            .iter()
+            .zip(&recovered.key)
+            .filter(|(a, b)| a == b)
+            .count();
+
+        assert_eq!(key.len(), recovered.key.len());
+        assert!(right >= key.len() - 2);
+
+        assert_eq!(None, break_repeating_key(b"a", 40));
+    }
+
+    #[test]
+    fn test_detect_single_byte() {
+        let mut candidates = (0..50_u8)
+            .map(|x| {
+                (0..30_u8)
+                    .map(|y| x.wrapping_mul(97).wrapping_add(y.wrapping_mul(31)))
+                    .collect()
+            })
+            .collect::<Vec<Vec<u8>>>();
+        candidates[37] = single_byte(PLAINTEXT, 0x35);
+
+        let detections = detect_single_byte(&candidates);
+
+        assert_eq!(50, detections.len());
+        assert_eq!(37, detections[0].index);
+        assert_eq!(0x35, detections[0].key);
+        assert_eq!(PLAINTEXT, detections[0].plaintext);
+        assert!(detections.windows(2).all(|x| x[0].score >= x[1].score));
+        assert!(detect_single_byte(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_drag_crib() {
+        let keystream = b"\x8f\x1d\x5a\xc3\x07\x99\x4e\x20\x6b\xd2\x13\x77\xa4\x58\x0c\xee\x31\x92\x4d\x6a\xf0\x15\x83\x2b\x5e\xc7\x08\x9a\x3d\x61";
+        let ciphertexts = [
+            b"meet me at the usual place".as_slice(),
+            b"attack the castle at dawn!",
+        ]
+        .iter()
+        .map(|x| xor_bytes(x, keystream))
+        .collect::<Vec<Vec<u8>>>();
+
+        let matches = drag_crib(&ciphertexts, b" the ");
+
+        assert_eq!(22, matches.len());
+        assert!(matches[0].is_plausible());
+        // " the " sits at 10 in the first plaintext and at 6 in the second
+        let best = matches
+            .iter()
+            .take_while(|x| x.is_plausible())
+            .map(|x| (x.offset, x.text.clone()))
+            .collect::<Vec<(usize, Vec<u8>)>>();
+        assert!(best.contains(&(10, b" cast".to_vec())));
+        assert!(best.contains(&(6, b"e at ".to_vec())));
+
+        assert!(drag_crib(&ciphertexts[..1], b"the").is_empty());
+        assert!(drag_crib(&ciphertexts, b"").is_empty());
+    }
+
+    #[test]
+    fn test_workbench() {
+        let keystream = [0x11, 0x22, 0x33, 0x44, 0x55, 0x66];
+        let mut workbench = Workbench::new(vec![
+            xor_bytes(b"hello!", &keystream),
+            xor_bytes(b"world", &keystream),
+        ]);
+
+        assert_eq!(vec![None; 6], workbench.plaintexts()[0]);
+
+        assert!(workbench.pin_plaintext(1, 1, b"orl"));
+        assert_eq!(
+            vec![None, Some(b'e'), Some(b'l'), Some(b'l'), None, None],
+            workbench.plaintexts()[0]
+        );
+
+        workbench.pin_key(4, &[0x55, 0x66, 0x77]);
+        assert_eq!(Some(b'!'), workbench.plaintexts()[0][5]);
+        assert_eq!(5, workbench.plaintexts()[1].len());
+
+        workbench.unpin(2, 10);
+        assert_eq!(
+            &[None, Some(0x22), None, None, None, None],
+            workbench.keystream()
+        );
+        assert!(!workbench.pin_plaintext(2, 0, b"x"));
+    }
+}